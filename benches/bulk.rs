@@ -0,0 +1,38 @@
+// cargo bench --bench bulk
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use redis_parser::Lexer;
+
+fn bulk_strings(payload_len: usize, count: usize) -> Vec<u8> {
+    let payload = "x".repeat(payload_len);
+    let mut buf = Vec::new();
+    for _ in 0..count {
+        buf.extend_from_slice(format!("${payload_len}\r\n{payload}\r\n").as_bytes());
+    }
+    buf
+}
+
+fn bulk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("bulk");
+    for (name, payload_len, count) in [
+        ("16B", 16, 1000),
+        ("1KiB", 1024, 100),
+        ("64KiB", 64 * 1024, 4),
+    ] {
+        let buf = bulk_strings(payload_len, count);
+        group.throughput(Throughput::Bytes(buf.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &buf[..], |b, buf| {
+            b.iter(|| {
+                for token in Lexer::new(black_box(buf)) {
+                    black_box(token.unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bulk);
+criterion_main!(benches);
@@ -0,0 +1,258 @@
+use alloc::vec::Vec;
+
+use crate::parser::{Error, Lexer, ParseOptions};
+use crate::resume::ResumableParser;
+use crate::value::Value;
+
+// 数据按任意大小的块到达, 不完整的尾部先缓存起来, 等后续的块补齐
+// 扫描到的位置会保存下来, 逐字节到达的frame不会每次都从头解析
+#[derive(Debug, Default)]
+pub struct ChunkedParser {
+    inner: ResumableParser,
+}
+
+impl ChunkedParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // max_bulk_len之类的限制在读到长度前缀时就会生效, 不用先缓存整个payload
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            inner: ResumableParser::with_options(options),
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.inner.feed(chunk);
+    }
+
+    // 还没有组成完整frame的字节数
+    pub fn pending(&self) -> usize {
+        self.inner.pending()
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    // 返回下一个完整的frame, 数据不够时返回None
+    pub fn next_frame(&mut self) -> Option<Result<Value, Error>> {
+        self.inner.next_frame()
+    }
+}
+
+// 环形缓冲区里的数据分成head和tail两段, 返回下一个frame和它消耗的字节数
+// Token要求数据是连续的, 所以只有跨过边界的frame才会拷贝, 其它frame直接在head里解析
+// 拷贝时只取tail开头frame需要的部分, 不会把整个tail都拷贝一遍
+// 数据不够一个完整的frame时返回None
+pub fn parse_split(head: &[u8], tail: &[u8]) -> Option<(Result<Value, Error>, usize)> {
+    parse_split_with_options(head, tail, ParseOptions::default())
+}
+
+pub fn parse_split_with_options(
+    head: &[u8],
+    tail: &[u8],
+    options: ParseOptions,
+) -> Option<(Result<Value, Error>, usize)> {
+    let mut lexer = Lexer::with_options(head, options);
+    match lexer.next() {
+        Some(Err(Error::Incomplete)) | None if !tail.is_empty() => {}
+        Some(Err(Error::Incomplete)) | None => return None,
+        Some(result) => return Some((result.and_then(Value::try_from), lexer.position())),
+    }
+    // 拷贝的长度每次翻倍, 总的拷贝和扫描次数和frame的长度成正比
+    let mut buf = Vec::with_capacity(head.len() + tail.len().min(MIN_SPLIT_COPY));
+    buf.extend_from_slice(head);
+    let mut taken = 0;
+    loop {
+        let take = (taken + buf.len().max(MIN_SPLIT_COPY)).min(tail.len());
+        buf.extend_from_slice(&tail[taken..take]);
+        taken = take;
+        let mut lexer = Lexer::with_options(&buf[..], options);
+        match lexer.next() {
+            Some(Err(Error::Incomplete)) | None if taken < tail.len() => {}
+            Some(Err(Error::Incomplete)) | None => return None,
+            Some(result) => return Some((result.and_then(Value::try_from), lexer.position())),
+        }
+    }
+}
+
+const MIN_SPLIT_COPY: usize = 64;
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_split, parse_split_with_options, ChunkedParser};
+    use crate::parser::{Error, ParserBuilder};
+    use crate::value::Value;
+
+    #[test]
+    fn test_push() {
+        let mut parser = ChunkedParser::new();
+        parser.push(b"*2\r\n$3");
+        assert_eq!(parser.next_frame(), None);
+        parser.push(b"\r\nfoo\r\n:");
+        assert_eq!(parser.next_frame(), None);
+        parser.push(b"1\r\n+OK\r\n+PA");
+        assert_eq!(
+            parser.next_frame(),
+            Some(Ok(Value::Array(Some(vec![
+                Value::BulkString(Some(b"foo".to_vec())),
+                Value::Integer(1),
+            ]))))
+        );
+        assert_eq!(
+            parser.next_frame(),
+            Some(Ok(Value::SimpleString("OK".to_string())))
+        );
+        assert_eq!(parser.next_frame(), None);
+        assert_eq!(parser.pending(), 3);
+    }
+
+    #[test]
+    fn test_push_byte_by_byte() {
+        let data = b"%1\r\n+key\r\n$5\r\nva\r\nl\r\n:-3\r\n";
+        let mut parser = ChunkedParser::new();
+        let mut values = Vec::new();
+        for byte in data {
+            parser.push(&[*byte]);
+            while let Some(value) = parser.next_frame() {
+                values.push(value.unwrap());
+            }
+        }
+        assert_eq!(
+            values,
+            vec![
+                Value::Map(Some(vec![
+                    Value::SimpleString("key".to_string()),
+                    Value::BulkString(Some(b"va\r\nl".to_vec())),
+                ])),
+                Value::Integer(-3),
+            ]
+        );
+        assert_eq!(parser.pending(), 0);
+    }
+
+    #[test]
+    fn test_parse_split() {
+        let data = b"+OK\r\n*2\r\n$3\r\nfoo\r\n:1\r\n";
+        // 第二个frame被环形缓冲区的边界分成两段
+        let (head, tail) = data.split_at(12);
+        assert_eq!(
+            parse_split(head, tail),
+            Some((Ok(Value::SimpleString("OK".to_string())), 5))
+        );
+        assert_eq!(
+            parse_split(&head[5..], tail),
+            Some((
+                Ok(Value::Array(Some(vec![
+                    Value::BulkString(Some(b"foo".to_vec())),
+                    Value::Integer(1),
+                ]))),
+                17
+            ))
+        );
+        assert_eq!(parse_split(&head[5..], &tail[..3]), None);
+        assert_eq!(parse_split(b"", b""), None);
+        assert_eq!(
+            parse_split(b"", b":1\r\n"),
+            Some((Ok(Value::Integer(1)), 4))
+        );
+    }
+
+    #[test]
+    fn test_malformed_terminator() {
+        // 格式错误的frame直接报错, 不会一直等待更多的数据
+        let mut parser = ChunkedParser::new();
+        parser.push(b":12x\r\n");
+        assert_eq!(parser.next_frame(), Some(Err(Error::MissingTerminator)));
+    }
+
+    #[test]
+    fn test_eof_marked_split() {
+        let mark = b"0123456789abcdef0123456789abcdef01234567";
+        let data = [
+            b"$EOF:".as_slice(),
+            mark,
+            b"\r\nREDIS0011",
+            mark,
+            b"+OK\r\n",
+        ]
+        .concat();
+        let expected = vec![
+            Value::BulkString(Some(b"REDIS0011".to_vec())),
+            Value::SimpleString("OK".to_string()),
+        ];
+        // 从每一个位置切开, 结果都和一次性收到时一样
+        for split in 0..=data.len() {
+            let (head, tail) = data.split_at(split);
+            let mut parser = ChunkedParser::new();
+            let mut values = Vec::new();
+            for chunk in [head, tail] {
+                parser.push(chunk);
+                while let Some(value) = parser.next_frame() {
+                    values.push(value.unwrap());
+                }
+            }
+            assert_eq!(values, expected, "split at {}", split);
+
+            let mut values = Vec::new();
+            let mut consumed = 0;
+            while let Some((value, len)) = parse_split(
+                &head[consumed.min(split)..],
+                &tail[consumed.saturating_sub(split)..],
+            ) {
+                values.push(value.unwrap());
+                consumed += len;
+            }
+            assert_eq!(values, expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_max_bulk_len() {
+        let options = ParserBuilder::new().max_bulk_len(1024).options();
+        // 只收到长度前缀就拒绝, 不会等512MB的payload
+        let mut parser = ChunkedParser::with_options(options);
+        parser.push(b"$536870912\r\n");
+        assert_eq!(
+            parser.next_frame(),
+            Some(Err(Error::BulkTooLarge(536870912)))
+        );
+
+        let mut parser = ChunkedParser::new();
+        parser.push(b"$536870912\r\n");
+        assert_eq!(parser.next_frame(), None);
+
+        assert_eq!(
+            parse_split_with_options(b"*1\r\n$5368", b"70912\r\n", options),
+            Some((Err(Error::BulkTooLarge(536870912)), 16))
+        );
+        assert_eq!(parse_split(b"*1\r\n$5368", b"70912\r\n"), None);
+    }
+
+    #[test]
+    fn test_parse_split_large_tail() {
+        // 跨过边界的frame比第一次拷贝的长度长, 后面还缓存着很多frame
+        let payload = "x".repeat(1000);
+        let frame = format!("*2\r\n${}\r\n{}\r\n:7\r\n", payload.len(), payload);
+        let data = [frame.as_bytes(), &b"+OK\r\n".repeat(10_000)].concat();
+        for split in [0, 1, 4, 10, 500, frame.len() - 1] {
+            let (head, tail) = data.split_at(split);
+            assert_eq!(
+                parse_split(head, tail),
+                Some((
+                    Ok(Value::Array(Some(vec![
+                        Value::BulkString(Some(payload.as_bytes().to_vec())),
+                        Value::Integer(7),
+                    ]))),
+                    frame.len()
+                )),
+                "split at {}",
+                split
+            );
+        }
+        let (head, tail) = frame.as_bytes().split_at(10);
+        assert_eq!(parse_split(head, &tail[..tail.len() - 1]), None);
+    }
+}
@@ -1 +1,260 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
 
+use crate::parser::Error;
+
+// 发给服务端的命令, 编码成由bulk string组成的array
+// *3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    args: Vec<Vec<u8>>,
+}
+
+impl Command {
+    pub fn new(name: impl AsRef<[u8]>) -> Self {
+        Self {
+            args: Vec::from([name.as_ref().to_vec()]),
+        }
+    }
+
+    // 参数按原样写入, 可以是二进制的key或者value
+    pub fn arg(mut self, arg: impl AsRef<[u8]>) -> Self {
+        self.args.push(arg.as_ref().to_vec());
+        self
+    }
+
+    pub fn encode(&self, buf: &mut Vec<u8>) {
+        write_header(buf, b'*', self.args.len());
+        for arg in &self.args {
+            write_header(buf, b'$', arg.len());
+            buf.extend_from_slice(arg);
+            buf.extend_from_slice(b"\r\n");
+        }
+    }
+
+    // redis-cli风格的inline命令, 比如 SET key "a b"
+    // 双引号里支持 \n \r \t \b \a \xHH 转义, 单引号里只支持 \'
+    pub fn parse_inline(line: &[u8]) -> Result<Self, Error> {
+        let mut args = split_inline(line)?.into_iter();
+        let name = args.next().ok_or(Error::MalformedInline)?;
+        Ok(args.fold(Command::new(name), Command::arg))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Command {
+    // 直接写到socket之类的Write, 不经过中间的Vec
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w, "*{}\r\n", self.args.len())?;
+        for arg in &self.args {
+            write!(w, "${}\r\n", arg.len())?;
+            w.write_all(arg)?;
+            w.write_all(b"\r\n")?;
+        }
+        Ok(())
+    }
+
+    // pipeline, 多个命令连续写入之后再读取回复
+    pub fn write_all_to<'c, W, I>(commands: I, w: &mut W) -> io::Result<()>
+    where
+        W: Write,
+        I: IntoIterator<Item = &'c Command>,
+    {
+        commands
+            .into_iter()
+            .try_for_each(|command| command.write_to(w))
+    }
+}
+
+fn split_inline(line: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    let mut args = Vec::new();
+    let mut iter = line.iter().copied().peekable();
+    loop {
+        while iter.next_if(u8::is_ascii_whitespace).is_some() {}
+        if iter.peek().is_none() {
+            return Ok(args);
+        }
+        let mut arg = Vec::new();
+        while let Some(c) = iter.next_if(|c| !c.is_ascii_whitespace()) {
+            match c {
+                b'"' => loop {
+                    match iter.next().ok_or(Error::MalformedInline)? {
+                        b'"' => break,
+                        b'\\' => arg.push(unescape(&mut iter)?),
+                        c => arg.push(c),
+                    }
+                },
+                b'\'' => loop {
+                    match iter.next().ok_or(Error::MalformedInline)? {
+                        b'\'' => break,
+                        b'\\' if iter.peek() == Some(&b'\'') => arg.push(iter.next().unwrap()),
+                        c => arg.push(c),
+                    }
+                },
+                c => {
+                    arg.push(c);
+                    continue;
+                }
+            }
+            // 和redis一样, 右引号后面必须是空白或者结尾
+            if iter.peek().is_some_and(|c| !c.is_ascii_whitespace()) {
+                return Err(Error::MalformedInline);
+            }
+        }
+        args.push(arg);
+    }
+}
+
+fn unescape(iter: &mut impl Iterator<Item = u8>) -> Result<u8, Error> {
+    let c = iter.next().ok_or(Error::MalformedInline)?;
+    let c = match c {
+        b'n' => b'\n',
+        b'r' => b'\r',
+        b't' => b'\t',
+        b'b' => 0x08,
+        b'a' => 0x07,
+        b'x' => {
+            let high = iter.next().and_then(hex_digit);
+            let low = iter.next().and_then(hex_digit);
+            match (high, low) {
+                (Some(high), Some(low)) => high << 4 | low,
+                _ => return Err(Error::MalformedInline),
+            }
+        }
+        c => c,
+    };
+    Ok(c)
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    char::from(c).to_digit(16).map(|digit| digit as u8)
+}
+
+pub(crate) fn write_header(buf: &mut Vec<u8>, symbol: u8, len: usize) {
+    buf.push(symbol);
+    buf.extend_from_slice(len.to_string().as_bytes());
+    buf.extend_from_slice(b"\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+    use crate::parser::{Error, Lexer, Token};
+
+    #[test]
+    fn test_encode() {
+        let mut buf = Vec::new();
+        Command::new("SET").arg("key").arg("value").encode(&mut buf);
+        assert_eq!(buf, b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n");
+
+        let mut buf = Vec::new();
+        Command::new("PING").encode(&mut buf);
+        assert_eq!(buf, b"*1\r\n$4\r\nPING\r\n");
+    }
+
+    #[test]
+    fn test_encode_binary() {
+        let mut buf = Vec::new();
+        Command::new("SET")
+            .arg(b"k\r\n\x00".as_slice())
+            .arg("")
+            .encode(&mut buf);
+        assert_eq!(buf, b"*3\r\n$3\r\nSET\r\n$4\r\nk\r\n\x00\r\n$0\r\n\r\n");
+
+        // 编码出来的字节可以被lexer解析回去
+        let mut buf = Vec::new();
+        Command::new("SET")
+            .arg(b"\xff\x00".as_slice())
+            .arg("")
+            .encode(&mut buf);
+        let mut lexer = Lexer::new(buf.as_slice());
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Array(Some(vec![
+                Token::BulkString(Some(b"SET".as_slice())),
+                Token::BulkString(Some(b"\xff\x00".as_slice())),
+                Token::BulkString(Some(b"".as_slice())),
+            ]))
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_write_to() {
+        let commands = [
+            Command::new("SET").arg("key").arg(b"\xff\r\n".as_slice()),
+            Command::new("GET").arg("key"),
+        ];
+        let mut buf = Vec::new();
+        Command::write_all_to(&commands, &mut buf).unwrap();
+
+        let mut encoded = Vec::new();
+        for command in &commands {
+            command.encode(&mut encoded);
+        }
+        assert_eq!(buf, encoded);
+
+        let mut lexer = Lexer::new(buf.as_slice());
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Array(Some(vec![
+                Token::BulkString(Some(b"SET".as_slice())),
+                Token::BulkString(Some(b"key".as_slice())),
+                Token::BulkString(Some(b"\xff\r\n".as_slice())),
+            ]))
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap(),
+            Token::Array(Some(vec![
+                Token::BulkString(Some(b"GET".as_slice())),
+                Token::BulkString(Some(b"key".as_slice())),
+            ]))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_parse_inline() {
+        assert_eq!(
+            Command::parse_inline(b"  SET key  value\r\n"),
+            Ok(Command::new("SET").arg("key").arg("value"))
+        );
+        assert_eq!(
+            Command::parse_inline(b"SET key \"a b\""),
+            Ok(Command::new("SET").arg("key").arg("a b"))
+        );
+        assert_eq!(
+            Command::parse_inline(b"SET key 'a \"b\" c'"),
+            Ok(Command::new("SET").arg("key").arg("a \"b\" c"))
+        );
+        assert_eq!(
+            Command::parse_inline(b"SET 'it\\'s' \"\\x41\\n\\\"\\\\\" \"\""),
+            Ok(Command::new("SET").arg("it's").arg("A\n\"\\").arg(""))
+        );
+        // 单引号里的其它反斜杠原样保留
+        assert_eq!(
+            Command::parse_inline(b"ECHO 'a\\nb'"),
+            Ok(Command::new("ECHO").arg("a\\nb"))
+        );
+    }
+
+    #[test]
+    fn test_parse_inline_malformed() {
+        assert_eq!(Command::parse_inline(b""), Err(Error::MalformedInline));
+        assert_eq!(Command::parse_inline(b"   "), Err(Error::MalformedInline));
+        assert_eq!(
+            Command::parse_inline(b"SET \"abc"),
+            Err(Error::MalformedInline)
+        );
+        assert_eq!(
+            Command::parse_inline(b"SET \"abc\"def"),
+            Err(Error::MalformedInline)
+        );
+        assert_eq!(
+            Command::parse_inline(b"SET \"\\x4\""),
+            Err(Error::MalformedInline)
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use crate::parser::Token;
+
+// HELLO 3 返回的握手信息
+#[derive(Debug, PartialEq)]
+pub struct HelloInfo<'a> {
+    pub server: &'a str,
+    pub version: &'a str,
+    pub proto: i64,
+    pub id: i64,
+    pub mode: &'a str,
+    pub role: &'a str,
+    pub modules: &'a [Token<'a>],
+}
+
+pub fn parse_hello<'a>(token: &'a Token<'a>) -> Option<HelloInfo<'a>> {
+    let map = match token {
+        Token::Map(Some(map)) => map,
+        _ => return None,
+    };
+
+    let mut server = None;
+    let mut version = None;
+    let mut proto = None;
+    let mut id = None;
+    let mut mode = None;
+    let mut role = None;
+    let mut modules: &'a [Token<'a>] = &[];
+
+    for pair in map.chunks_exact(2) {
+        let value = &pair[1];
+        match pair[0].as_str()? {
+            "server" => server = value.as_str(),
+            "version" => version = value.as_str(),
+            "proto" => proto = integer(value),
+            "id" => id = integer(value),
+            "mode" => mode = value.as_str(),
+            "role" => role = value.as_str(),
+            "modules" => {
+                if let Token::Array(Some(list)) = value {
+                    modules = list;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(HelloInfo {
+        server: server?,
+        version: version?,
+        proto: proto?,
+        id: id?,
+        mode: mode?,
+        role: role?,
+        modules,
+    })
+}
+
+fn integer(token: &Token) -> Option<i64> {
+    match token {
+        Token::Integer(value) => Some(*value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_hello, HelloInfo};
+    use crate::parser::{Lexer, Token};
+
+    #[test]
+    fn test_parse_hello() {
+        let mut lexer = Lexer::new(
+            "%7\r\n\
+             $6\r\nserver\r\n$5\r\nredis\r\n\
+             $7\r\nversion\r\n$5\r\n7.2.4\r\n\
+             $5\r\nproto\r\n:3\r\n\
+             $2\r\nid\r\n:5\r\n\
+             $4\r\nmode\r\n$10\r\nstandalone\r\n\
+             $4\r\nrole\r\n$6\r\nmaster\r\n\
+             $7\r\nmodules\r\n*0\r\n",
+        );
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(
+            parse_hello(&token),
+            Some(HelloInfo {
+                server: "redis",
+                version: "7.2.4",
+                proto: 3,
+                id: 5,
+                mode: "standalone",
+                role: "master",
+                modules: &[],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_hello_missing_field() {
+        let mut lexer = Lexer::new("%1\r\n$6\r\nserver\r\n$5\r\nredis\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(parse_hello(&token), None);
+        assert_eq!(parse_hello(&Token::SimpleString("OK")), None);
+    }
+}
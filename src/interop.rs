@@ -0,0 +1,205 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use redis::{PushKind, Value as RedisValue, VerbatimFormat};
+
+use crate::parser::Token;
+use crate::value::Value;
+
+// ServerError没有公开的构造函数, BigNumber的内容又取决于redis的num-bigint feature
+// 这两种交给redis自己的解析器处理, 保证和redis-rs解析出来的完全一样
+fn reparse(frame: &[u8], text: String) -> RedisValue {
+    redis::parse_redis_value(frame).unwrap_or(RedisValue::SimpleString(text))
+}
+
+fn server_error(text: String) -> RedisValue {
+    let mut frame = Vec::with_capacity(text.len() + 16);
+    frame.push(b'!');
+    frame.extend_from_slice(text.len().to_string().as_bytes());
+    frame.extend_from_slice(b"\r\n");
+    frame.extend_from_slice(text.as_bytes());
+    frame.extend_from_slice(b"\r\n");
+    reparse(&frame, text)
+}
+
+fn big_number(text: String) -> RedisValue {
+    let frame = [b"(", text.as_bytes(), b"\r\n"].concat();
+    reparse(&frame, text)
+}
+
+// 和redis-rs的get_push_kind一致
+fn push_kind(kind: String) -> PushKind {
+    match kind.as_str() {
+        "invalidate" => PushKind::Invalidate,
+        "message" => PushKind::Message,
+        "pmessage" => PushKind::PMessage,
+        "smessage" => PushKind::SMessage,
+        "unsubscribe" => PushKind::Unsubscribe,
+        "punsubscribe" => PushKind::PUnsubscribe,
+        "sunsubscribe" => PushKind::SUnsubscribe,
+        "subscribe" => PushKind::Subscribe,
+        "psubscribe" => PushKind::PSubscribe,
+        "ssubscribe" => PushKind::SSubscribe,
+        _ => PushKind::Other(kind),
+    }
+}
+
+fn list(list: Vec<Value>) -> Vec<RedisValue> {
+    list.into_iter().map(RedisValue::from).collect()
+}
+
+fn pairs(list: Vec<Value>) -> Vec<(RedisValue, RedisValue)> {
+    let mut list = list.into_iter().map(RedisValue::from);
+    let mut pairs = Vec::with_capacity(list.len() / 2);
+    while let (Some(key), Some(value)) = (list.next(), list.next()) {
+        pairs.push((key, value));
+    }
+    pairs
+}
+
+// 和redis-rs解析同一个回复的结果保持一致: +OK转成Okay, 各种null都转成Nil
+// redis::Value没有对应的ArrayHeader, 只保留长度; 单独的attribute没有修饰的数据, data为Nil
+impl From<Value> for RedisValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::SimpleString(text) if text == "OK" => RedisValue::Okay,
+            Value::SimpleString(text) => RedisValue::SimpleString(text),
+            Value::Error(text) | Value::BigErr(text) => server_error(text),
+            Value::Integer(value) => RedisValue::Int(value),
+            Value::BulkString(Some(bytes)) => RedisValue::BulkString(bytes),
+            Value::Array(Some(values)) => RedisValue::Array(list(values)),
+            Value::Boolean(value) => RedisValue::Boolean(value),
+            Value::Set(Some(values)) => RedisValue::Set(list(values)),
+            Value::Double(text) => match text.parse() {
+                Ok(value) => RedisValue::Double(value),
+                Err(_) => RedisValue::SimpleString(text),
+            },
+            Value::BigNumber(text) => big_number(text),
+            Value::VerbatimString(format, text) => RedisValue::VerbatimString {
+                format: match format.as_str() {
+                    "txt" => VerbatimFormat::Text,
+                    "mkd" => VerbatimFormat::Markdown,
+                    _ => VerbatimFormat::Unknown(format),
+                },
+                text,
+            },
+            Value::Map(Some(values)) => RedisValue::Map(pairs(values)),
+            // 第一个元素是push的类型
+            Value::Push(Some(mut values)) => {
+                let kind = match values.first() {
+                    Some(Value::SimpleString(kind)) => Some(kind.clone()),
+                    Some(Value::BulkString(Some(kind))) => {
+                        Some(String::from_utf8_lossy(kind).into_owned())
+                    }
+                    _ => None,
+                };
+                let kind = match kind {
+                    Some(kind) => {
+                        values.remove(0);
+                        push_kind(kind)
+                    }
+                    None => PushKind::Other(String::new()),
+                };
+                RedisValue::Push {
+                    kind,
+                    data: list(values),
+                }
+            }
+            Value::ArrayHeader(len) => RedisValue::Int(len as i64),
+            Value::Attribute(values) => RedisValue::Attribute {
+                data: Box::new(RedisValue::Nil),
+                attributes: pairs(values),
+            },
+            Value::BulkString(None)
+            | Value::Array(None)
+            | Value::Set(None)
+            | Value::Map(None)
+            | Value::Push(None)
+            | Value::Null => RedisValue::Nil,
+        }
+    }
+}
+
+impl From<Token<'_>> for RedisValue {
+    fn from(token: Token<'_>) -> Self {
+        RedisValue::from(token.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::{PushKind, Value as RedisValue, VerbatimFormat};
+
+    use crate::parser::Lexer;
+    use crate::value::parse_all;
+
+    #[test]
+    fn test_nested_reply() {
+        let input = "*4\r\n+OK\r\n%2\r\n$3\r\nfoo\r\n*2\r\n:1\r\n_\r\n+bar\r\n~1\r\n#t\r\n\
+                     $-1\r\n=8\r\ntxt:text\r\n";
+        let mut lexer = Lexer::new(input);
+        let value = RedisValue::from(lexer.next().unwrap().unwrap());
+        assert_eq!(
+            value,
+            RedisValue::Array(vec![
+                RedisValue::Okay,
+                RedisValue::Map(vec![
+                    (
+                        RedisValue::BulkString(b"foo".to_vec()),
+                        RedisValue::Array(vec![RedisValue::Int(1), RedisValue::Nil]),
+                    ),
+                    (
+                        RedisValue::SimpleString("bar".to_string()),
+                        RedisValue::Set(vec![RedisValue::Boolean(true)]),
+                    ),
+                ]),
+                RedisValue::Nil,
+                RedisValue::VerbatimString {
+                    format: VerbatimFormat::Text,
+                    text: "text".to_string(),
+                },
+            ])
+        );
+        assert_eq!(redis::parse_redis_value(input.as_bytes()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_same_as_redis() {
+        // 和redis-rs自己解析出来的结果一样
+        let inputs: [&[u8]; 7] = [
+            b"-ERR unknown command\r\n",
+            b"!21\r\nSYNTAX invalid syntax\r\n",
+            b",1.5\r\n",
+            b"(3492890328409238509324850943850943825024385\r\n",
+            b">3\r\n$7\r\nmessage\r\n$2\r\nch\r\n$5\r\nhello\r\n",
+            b"*-1\r\n",
+            b"$3\r\n\xff\x00a\r\n",
+        ];
+        for input in inputs {
+            let value = parse_all(input).unwrap().remove(0);
+            assert_eq!(
+                RedisValue::from(value),
+                redis::parse_redis_value(input).unwrap(),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_push_kind() {
+        let value = parse_all(b">2\r\n+invalidate\r\n*1\r\n$3\r\nkey\r\n")
+            .unwrap()
+            .remove(0);
+        assert_eq!(
+            RedisValue::from(value),
+            RedisValue::Push {
+                kind: PushKind::Invalidate,
+                data: vec![RedisValue::Array(vec![RedisValue::BulkString(
+                    b"key".to_vec()
+                )])],
+            }
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde_json::{Map, Number, Value as Json};
+
+use crate::parser::{Input, Token};
+
+// 文本按utf8解码, 不合法的字节替换成U+FFFD
+fn lossy<S: ?Sized + Input>(text: &S) -> Cow<'_, str> {
+    String::from_utf8_lossy(text.as_bytes())
+}
+
+fn list<S: ?Sized + Input>(list: &[Token<'_, S>]) -> Json {
+    Json::Array(list.iter().map(Token::to_json).collect())
+}
+
+// 键值交替排列, 键统一转成字符串, 重复的键后面的覆盖前面的
+fn object<S: ?Sized + Input>(list: &[Token<'_, S>]) -> Json {
+    let mut map = Map::new();
+    for pair in list.chunks(2) {
+        let value = pair.get(1).map_or(Json::Null, Token::to_json);
+        map.insert(key(&pair[0]), value);
+    }
+    Json::Object(map)
+}
+
+fn key<S: ?Sized + Input>(token: &Token<'_, S>) -> String {
+    match token.to_json() {
+        Json::String(text) => text,
+        json => json.to_string(),
+    }
+}
+
+impl<S: ?Sized + Input> Token<'_, S> {
+    // 约定: map转成对象, 键转成字符串; bulk string按utf8有损解码; null转成Null
+    // big number保留成字符串, 避免丢失精度; 无穷和NaN不是合法的JSON数字, 也保留成字符串
+    pub fn to_json(&self) -> Json {
+        match self {
+            Token::SimpleString(text) | Token::Error(text) | Token::BigErr(text) => {
+                Json::String(lossy(*text).into_owned())
+            }
+            Token::Integer(value) => Json::from(*value),
+            Token::BulkString(Some(text)) => Json::String(lossy(*text).into_owned()),
+            Token::Boolean(value) => Json::Bool(*value),
+            Token::Double(text) => text
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map_or_else(|| Json::String(text.to_string()), Json::Number),
+            Token::BigNumber(text) => Json::String(text.to_string()),
+            Token::VerbatimString(_, text) => Json::String(lossy(*text).into_owned()),
+            Token::Array(Some(values)) | Token::Set(Some(values)) | Token::Push(Some(values)) => {
+                list(values)
+            }
+            Token::Map(Some(values)) | Token::Attribute(values) => object(values),
+            Token::ArrayHeader(len) => Json::from(*len),
+            Token::StreamedString(chunks) => {
+                let bytes: Vec<u8> = chunks
+                    .iter()
+                    .flat_map(|chunk| chunk.as_bytes())
+                    .copied()
+                    .collect();
+                Json::String(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            Token::BulkString(None)
+            | Token::Array(None)
+            | Token::Set(None)
+            | Token::Map(None)
+            | Token::Push(None)
+            | Token::Null => Json::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::parser::{Lexer, Token};
+
+    fn to_json(input: &[u8]) -> serde_json::Value {
+        Lexer::new(input).next().unwrap().unwrap().to_json()
+    }
+
+    #[test]
+    fn test_simple() {
+        assert_eq!(to_json(b"+OK\r\n"), json!("OK"));
+        assert_eq!(to_json(b"-ERR unknown\r\n"), json!("ERR unknown"));
+        assert_eq!(to_json(b"!9\r\nSYNTAX no\r\n"), json!("SYNTAX no"));
+        assert_eq!(to_json(b":-42\r\n"), json!(-42));
+        assert_eq!(to_json(b"#t\r\n"), json!(true));
+        assert_eq!(to_json(b"_\r\n"), json!(null));
+    }
+
+    #[test]
+    fn test_bulk_string() {
+        assert_eq!(to_json(b"$3\r\nfoo\r\n"), json!("foo"));
+        assert_eq!(to_json(b"$2\r\n\xffa\r\n"), json!("\u{fffd}a"));
+        assert_eq!(to_json(b"$-1\r\n"), json!(null));
+        assert_eq!(to_json(b"=15\r\ntxt:Some string\r\n"), json!("Some string"));
+        assert_eq!(
+            to_json(b"$?\r\n;2\r\nab\r\n;1\r\nc\r\n;0\r\n"),
+            json!("abc")
+        );
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(to_json(b",1.5\r\n"), json!(1.5));
+        assert_eq!(to_json(b",-2e3\r\n"), json!(-2000.0));
+        assert_eq!(
+            to_json(b"(3492890328409238509324850943850943825024385\r\n"),
+            json!("3492890328409238509324850943850943825024385")
+        );
+        let token: Token = Token::Double("inf");
+        assert_eq!(token.to_json(), json!("inf"));
+        assert_eq!(Token::<str>::ArrayHeader(3).to_json(), json!(3));
+    }
+
+    #[test]
+    fn test_aggregates() {
+        assert_eq!(
+            to_json(b"*3\r\n:1\r\n$-1\r\n*1\r\n+a\r\n"),
+            json!([1, null, ["a"]])
+        );
+        assert_eq!(to_json(b"*-1\r\n"), json!(null));
+        assert_eq!(to_json(b"~2\r\n#f\r\n:2\r\n"), json!([false, 2]));
+        assert_eq!(
+            to_json(b">2\r\n+message\r\n+hi\r\n"),
+            json!(["message", "hi"])
+        );
+        assert_eq!(
+            to_json(b"%3\r\n+a\r\n:1\r\n:2\r\n*0\r\n#t\r\n_\r\n"),
+            json!({"a": 1, "2": [], "true": null})
+        );
+        let attribute: Token =
+            Token::Attribute(vec![Token::SimpleString("ttl"), Token::Integer(5)]);
+        assert_eq!(attribute.to_json(), json!({"ttl": 5}));
+    }
+}
@@ -1,2 +1,38 @@
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+extern crate alloc;
+
+mod chunked;
 mod cmd;
+mod hello;
+#[cfg(feature = "redis")]
+mod interop;
+#[cfg(feature = "json")]
+mod json;
+mod owned;
 mod parser;
+#[cfg(feature = "std")]
+mod read;
+mod redirect;
+mod resume;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "tokio")]
+mod stream;
+mod value;
+
+pub use chunked::{parse_split, parse_split_with_options, ChunkedParser};
+pub use cmd::Command;
+pub use hello::{parse_hello, HelloInfo};
+pub use owned::OwnedParser;
+pub use parser::{
+    Error, Frames, Input, Lexer, ParseOptions, ParseStats, Parser, ParserBuilder, Protocol,
+    RedisError, RespType, Spanned, Token, VerbatimFormat, Visitor, WithAttributes,
+};
+#[cfg(feature = "std")]
+pub use read::{parse_resp_file, Capture, ReadParser};
+pub use redirect::{Redirect, RedirectKind};
+pub use resume::ResumableParser;
+#[cfg(feature = "tokio")]
+pub use stream::AsyncReadParser;
+pub use value::{parse_all, Value, PARSE_ALL_MAX_DEPTH};
@@ -0,0 +1,106 @@
+use alloc::vec::Vec;
+use core::iter::FusedIterator;
+
+use crate::parser::{Error, Lexer, ParseOptions, Protocol};
+use crate::value::Value;
+
+// 持有数据的parser, 返回owned的Value, 调用方不需要保证原始数据一直存活
+#[derive(Debug, Clone)]
+pub struct OwnedParser {
+    buf: Vec<u8>,
+    position: usize,
+    options: ParseOptions,
+    done: bool,
+}
+
+impl OwnedParser {
+    pub fn new(buf: impl Into<Vec<u8>>) -> Self {
+        Self::with_options(buf, ParseOptions::default())
+    }
+
+    pub fn with_options(buf: impl Into<Vec<u8>>, options: ParseOptions) -> Self {
+        Self {
+            buf: buf.into(),
+            position: 0,
+            options,
+            done: false,
+        }
+    }
+
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.options.protocol = protocol;
+    }
+
+    // 已经消耗的字节数
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+impl Iterator for OwnedParser {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut lexer = Lexer::with_options(&self.buf[self.position..], self.options);
+        let result = match lexer.next() {
+            Some(result) => result.and_then(Value::try_from),
+            None => {
+                self.done = true;
+                return None;
+            }
+        };
+        // 数据不会再增加, 不完整的frame只报告一次
+        if result == Err(Error::Incomplete) {
+            self.done = true;
+        }
+        self.position += lexer.position();
+        Some(result)
+    }
+}
+
+impl FusedIterator for OwnedParser {}
+
+#[cfg(test)]
+mod tests {
+    use super::OwnedParser;
+    use crate::parser::Error;
+    use crate::value::Value;
+
+    #[test]
+    fn test_owned_parser() {
+        let data = String::from("+OK\r\n:1000\r\n*2\r\n$3\r\nfoo\r\n_\r\n");
+        let parser = OwnedParser::new(data);
+        let values: Result<Vec<_>, _> = parser.collect();
+        assert_eq!(
+            values.unwrap(),
+            vec![
+                Value::SimpleString("OK".to_string()),
+                Value::Integer(1000),
+                Value::Array(Some(vec![
+                    Value::BulkString(Some(b"foo".to_vec())),
+                    Value::Null,
+                ])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_owned_parser_bytes() {
+        let mut parser = OwnedParser::new(b"$3\r\n\xff\x00\x01\r\n+PA".to_vec());
+        assert_eq!(
+            parser.next(),
+            Some(Ok(Value::BulkString(Some(vec![0xff, 0x00, 0x01]))))
+        );
+        assert_eq!(parser.position(), 9);
+        assert_eq!(parser.next(), Some(Err(Error::Incomplete)));
+        assert_eq!(parser.next(), None);
+        assert_eq!(parser.into_inner().len(), 12);
+    }
+}
@@ -1,610 +1,4617 @@
-use std::iter::{Iterator, Peekable};
-use std::num::ParseFloatError;
-use std::num::ParseIntError;
-use std::str::CharIndices;
-use std::str::FromStr;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::{FusedIterator, Iterator};
+use core::marker::PhantomData;
+use core::num::ParseFloatError;
+use core::num::{IntErrorKind, ParseIntError};
+use core::ops::Range;
+use core::str::FromStr;
+use core::str::Utf8Error;
+#[cfg(feature = "bigint")]
+use num_bigint::{BigInt, ParseBigIntError};
 
-#[derive(Debug, PartialEq)]
-enum Token<'a> {
-    SimpleString(&'a str),
-    Error(&'a str),
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum Token<'a, S: ?Sized = str> {
+    SimpleString(&'a S),
+    Error(&'a S),
     Integer(i64),
-    BulkString(Option<&'a str>),
-    Array(Option<Vec<Token<'a>>>),
+    BulkString(Option<&'a S>),
+    Array(Option<Vec<Token<'a, S>>>),
     Boolean(bool),
-    Set(Option<Vec<Token<'a>>>),
+    Set(Option<Vec<Token<'a, S>>>),
     Double(&'a str),
     BigNumber(&'a str),
-    BigErr(&'a str),
-    VerbatimString(&'a str, &'a str),
-    Map(Option<Vec<Token<'a>>>),
-    Push(Option<Vec<Token<'a>>>),
-}
-
-#[derive(Debug, PartialEq)]
-enum Error {
-    I64(ParseIntError),
-    F64(ParseFloatError),
-    Boolean,
+    BigErr(&'a S),
+    VerbatimString(&'a S, &'a S),
+    Map(Option<Vec<Token<'a, S>>>),
+    Push(Option<Vec<Token<'a, S>>>),
+    // streaming模式下只返回数组的长度, 元素由后续的next返回
+    ArrayHeader(usize),
+    Null,
+    // $?开头的streamed string, 各个chunk在buffer里不连续
+    StreamedString(Vec<&'a S>),
+    // keep_attributes模式下返回的attribute, 和Map一样键值交替排列, 后面紧跟被修饰的token
+    Attribute(Vec<Token<'a, S>>),
 }
 
-impl From<ParseIntError> for Error {
-    fn from(err: ParseIntError) -> Self {
-        Error::I64(err)
+// derive会要求S: Clone, str和[u8]都不满足, 所以手动实现
+impl<S: ?Sized> Clone for Token<'_, S> {
+    fn clone(&self) -> Self {
+        match self {
+            Token::SimpleString(text) => Token::SimpleString(text),
+            Token::Error(text) => Token::Error(text),
+            Token::Integer(value) => Token::Integer(*value),
+            Token::BulkString(text) => Token::BulkString(*text),
+            Token::Array(list) => Token::Array(list.clone()),
+            Token::Boolean(value) => Token::Boolean(*value),
+            Token::Set(list) => Token::Set(list.clone()),
+            Token::Double(text) => Token::Double(text),
+            Token::BigNumber(text) => Token::BigNumber(text),
+            Token::BigErr(text) => Token::BigErr(text),
+            Token::VerbatimString(format, text) => Token::VerbatimString(format, text),
+            Token::Map(list) => Token::Map(list.clone()),
+            Token::Push(list) => Token::Push(list.clone()),
+            Token::ArrayHeader(len) => Token::ArrayHeader(*len),
+            Token::Null => Token::Null,
+            Token::StreamedString(chunks) => Token::StreamedString(chunks.clone()),
+            Token::Attribute(list) => Token::Attribute(list.clone()),
+        }
     }
 }
 
-impl From<ParseFloatError> for Error {
-    fn from(err: ParseFloatError) -> Self {
-        Error::F64(err)
+impl<'a> Token<'a> {
+    // 字符串类的token返回其文本, verbatim string只返回文本部分
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Token::SimpleString(text)
+            | Token::Error(text)
+            | Token::BulkString(Some(text))
+            | Token::BigErr(text)
+            | Token::VerbatimString(_, text) => Some(text),
+            _ => None,
+        }
     }
-}
 
-type ParseResult<T> = std::result::Result<T, Error>;
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        self.as_str().map(str::as_bytes)
+    }
 
-#[derive(Debug)]
-struct Lexer<'a> {
-    inner: &'a str,
-    scanner: Peekable<CharIndices<'a>>,
-    position: usize,
-}
+    // 错误信息开头的大写单词, 比如ERR, WRONGTYPE, MOVED
+    pub fn error_code(&self) -> Option<&'a str> {
+        self.split_error().map(|(code, _)| code)
+    }
 
-impl<'a> Lexer<'a> {
-    fn new(inner: &'a str) -> Self {
-        Self {
-            inner,
-            scanner: inner.char_indices().peekable(),
-            position: 0,
+    pub fn verbatim_format(&self) -> Option<VerbatimFormat<'a>> {
+        match self {
+            Token::VerbatimString(format, _) => Some(VerbatimFormat::from(*format)),
+            _ => None,
         }
     }
 
-    fn skip_line(&mut self) -> Option<()> {
-        if self.inner.get(self.position..=self.position + 1).is_some() {
-            self.next_if(|(_, c)| *c == '\r');
-            self.next_if(|(_, c)| *c == '\n');
-            Some(())
+    // -ERR和!两种错误统一返回, 没有错误码时code为空
+    pub fn as_redis_error(&self) -> Option<RedisError<'a>> {
+        let text = match self {
+            Token::Error(text) | Token::BigErr(text) => *text,
+            _ => return None,
+        };
+        let (code, message) = self.split_error().unwrap_or(("", text));
+        Some(RedisError { code, message })
+    }
+
+    pub fn split_error(&self) -> Option<(&'a str, &'a str)> {
+        let text = match self {
+            Token::Error(text) | Token::BigErr(text) => *text,
+            _ => return None,
+        };
+        let (code, message) = text.split_once(' ').unwrap_or((text, ""));
+        if !code.is_empty() && code.chars().all(|c| c.is_ascii_uppercase()) {
+            Some((code, message))
         } else {
             None
         }
     }
+}
 
-    fn scan_token(&mut self, target_char: char) -> Option<(usize, char)> {
-        self.next_if(|(_, c)| *c == target_char)
-    }
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RedisError<'a> {
+    pub code: &'a str,
+    pub message: &'a str,
+}
 
-    fn next_if<F>(&mut self, condition: F) -> Option<(usize, char)>
-    where
-        F: FnOnce(&(usize, char)) -> bool,
-    {
-        self.scanner.next_if(condition).map(|c| {
-            self.position = c.0 + 1;
-            c
-        })
+// verbatim string的格式, txt是纯文本, mkd是markdown
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum VerbatimFormat<'a> {
+    Text,
+    Markdown,
+    Other(&'a str),
+}
+
+impl<'a> From<&'a str> for VerbatimFormat<'a> {
+    fn from(format: &'a str) -> Self {
+        match format {
+            "txt" => VerbatimFormat::Text,
+            "mkd" => VerbatimFormat::Markdown,
+            _ => VerbatimFormat::Other(format),
+        }
     }
+}
 
-    fn scan_string<F>(&mut self, condition: F) -> Option<&'a str>
-    where
-        F: FnOnce(&(usize, char)) -> bool + Copy,
-    {
-        let start_position = self.position;
-        let mut end_position = self.position;
-        while let Some((position, _)) = self.scanner.next_if(condition) {
-            end_position = position;
+impl<'a> Token<'a, [u8]> {
+    // 字节lexer的字符串类token, 检查utf8之后返回文本
+    pub fn try_as_str(&self) -> Option<Result<&'a str, Utf8Error>> {
+        match self {
+            Token::SimpleString(text)
+            | Token::Error(text)
+            | Token::BulkString(Some(text))
+            | Token::BigErr(text)
+            | Token::VerbatimString(_, text) => Some(core::str::from_utf8(text)),
+            _ => None,
         }
-        let text = if start_position < end_position {
-            self.position = end_position + 1;
-            self.inner.get(start_position..=end_position)?
-        } else {
-            ""
+    }
+
+    // KEYS, SMEMBERS这类返回bulk string数组的命令, 出错时带上元素的下标
+    pub fn try_as_utf8_array(&self) -> ParseResult<Vec<&'a str>> {
+        let list = match self {
+            Token::Array(Some(list)) | Token::Set(Some(list)) => list,
+            _ => {
+                return Err(Error::TypeMismatch {
+                    expected: "array",
+                    found: self.type_name(),
+                })
+            }
         };
-        Some(text)
+        list.iter()
+            .enumerate()
+            .map(|(index, token)| match token {
+                Token::BulkString(Some(text)) => {
+                    core::str::from_utf8(text).map_err(|error| Error::ElementUtf8 { index, error })
+                }
+                _ => Err(Error::TypeMismatch {
+                    expected: "bulk_string",
+                    found: token.type_name(),
+                }),
+            })
+            .collect()
     }
+}
 
-    fn get_symbol_position(&mut self) -> usize {
-        self.next_if(|(_, c)| *c == '+' || *c == '-')
-            .unwrap_or((self.position, '+'))
-            .0
+impl<S: ?Sized> Token<'_, S> {
+    // 聚合类型的元素数量, map返回键值对的数量
+    pub fn len(&self) -> Option<usize> {
+        match self {
+            Token::Array(Some(list)) | Token::Set(Some(list)) | Token::Push(Some(list)) => {
+                Some(list.len())
+            }
+            Token::Map(Some(map)) | Token::Attribute(map) => Some(map.len() / 2),
+            Token::ArrayHeader(len) => Some(*len),
+            _ => None,
+        }
     }
 
-    fn scan_number(&mut self) -> (usize, usize) {
-        let start_position = self.position;
-        let mut end_position = self.position;
-        while let Some((position, _)) = self.scanner.next_if(|(_, c)| c.is_ascii_digit()) {
-            end_position = position;
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    // Double保留原始文本, 需要数值时再解析
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Token::Double(text) => f64::from_str(text).ok(),
+            _ => None,
         }
-        (start_position, end_position)
     }
 
-    fn get_integer(&mut self) -> Option<ParseResult<i64>> {
-        let symbol_position = self.get_symbol_position();
-        let (_, end_position) = self.scan_number();
-        let text = self.inner.get(symbol_position..=end_position)?;
-        Some(i64::from_str(text).map_err(Error::I64))
+    // RESP2和RESP3下同一个命令可能返回integer或者double, 两种都接受
+    pub fn as_number_f64(&self) -> Option<f64> {
+        match self {
+            Token::Integer(value) => Some(*value as f64),
+            _ => self.as_f64(),
+        }
     }
 
-    fn get_collections<F>(
-        &mut self,
-        count_result: ParseResult<i64>,
-        mut call_back: F,
-    ) -> Option<ParseResult<i64>>
-    where
-        F: FnMut(Token<'a>),
-    {
-        match count_result {
-            Err(e) => Some(Err(e)),
-            Ok(count) => {
-                if count >= 0 {
-                    let tmp_count = count as usize;
-                    for _ in 0..tmp_count {
-                        match self.next()? {
-                            Ok(token) => call_back(token),
-                            Err(e) => return Some(Err(e)),
-                        }
-                    }
-                    Some(Ok(count))
-                } else {
-                    self.skip_line()?;
-                    Some(Ok(count))
+    // double只有是整数并且在i64范围内时才转换
+    pub fn as_number_i64(&self) -> Option<i64> {
+        match self {
+            Token::Integer(value) => Some(*value),
+            Token::Double(_) => {
+                let value = self.as_f64()?;
+                // i64::MAX as f64 会进位成2^63, 所以上界不能取等号
+                if !(value >= i64::MIN as f64 && value < i64::MAX as f64) {
+                    return None;
                 }
+                let integer = value as i64;
+                (integer as f64 == value).then_some(integer)
             }
+            _ => None,
         }
     }
 
-    fn scan_simple_string(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('+')?;
-        let text = self.scan_string(|(_, c)| *c != '\r' && *c != '\n')?;
-        self.skip_line()?;
-        Some(Ok(Token::SimpleString(text)))
+    // i128范围内的big number, 超出范围时返回None
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Token::BigNumber(text) => i128::from_str(text).ok(),
+            Token::Integer(value) => Some(i128::from(*value)),
+            _ => None,
+        }
     }
 
-    fn scan_error(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('-')?;
-        let text = self.scan_string(|(_, c)| *c != '\r' && *c != '\n')?;
-        self.skip_line()?;
-        Some(Ok(Token::Error(text)))
+    // 任意精度的big number, 开头的+和-都按符号处理
+    #[cfg(feature = "bigint")]
+    pub fn as_bigint(&self) -> Option<Result<BigInt, ParseBigIntError>> {
+        match self {
+            Token::BigNumber(text) => Some(BigInt::from_str(text)),
+            Token::Integer(value) => Some(Ok(BigInt::from(*value))),
+            _ => None,
+        }
     }
 
-    fn scan_integer(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token(':')?;
-        let result = self.get_integer()?;
-        self.skip_line()?;
-        Some(result.map(Token::Integer))
+    // 按redis的规则重新格式化, 5.9e3 => 5900
+    pub fn to_resp3_string(&self) -> Option<String> {
+        self.as_f64().map(format_double)
     }
 
-    fn scan_bulk_string(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('$')?;
-        let count_result = self.get_integer()?;
-        self.skip_line()?;
+    // 最深的嵌套层数, 标量和空的聚合类型都是1
+    pub fn depth(&self) -> usize {
+        match self {
+            Token::Array(Some(list))
+            | Token::Set(Some(list))
+            | Token::Map(Some(list))
+            | Token::Push(Some(list))
+            | Token::Attribute(list) => 1 + list.iter().map(Token::depth).max().unwrap_or(0),
+            _ => 1,
+        }
+    }
 
-        match count_result {
-            Ok(count) => {
-                if count >= 0 {
-                    let count = count as usize;
-                    let end_position = self.position + count;
-                    let text = self.scan_string(|(position, c)| {
-                        *position < end_position && *c != '\r' && *c != '\n'
-                    })?;
-                    self.skip_line()?;
-                    Some(Ok(Token::BulkString(Some(text))))
-                } else {
-                    Some(Ok(Token::BulkString(None)))
+    // 层层解开只有一个元素的数组, 其它token原样返回
+    pub fn flatten(self) -> Self {
+        let mut token = self;
+        loop {
+            match token {
+                Token::Array(Some(mut list)) if list.len() == 1 => {
+                    token = list.pop().unwrap();
                 }
+                other => return other,
             }
-            Err(e) => Some(Err(e)),
         }
     }
 
-    fn scan_array(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('*')?;
-        let count_result = self.get_integer()?;
-        self.skip_line()?;
+    // 先序遍历, 先访问聚合类型本身, 再依次访问里面的元素
+    pub fn walk<F>(&self, mut f: F)
+    where
+        F: FnMut(&Self),
+    {
+        self.walk_with(&mut f);
+    }
 
-        let mut list = Vec::new();
-        match self.get_collections(count_result, |token| list.push(token)) {
-            None => None,
-            Some(Ok(count)) if count >= 0 => Some(Ok(Token::Array(Some(list)))),
-            Some(Ok(_)) => Some(Ok(Token::Array(None))),
-            Some(Err(e)) => Some(Err(e)),
+    fn walk_with<F>(&self, f: &mut F)
+    where
+        F: FnMut(&Self),
+    {
+        f(self);
+        if let Token::Array(Some(list))
+        | Token::Set(Some(list))
+        | Token::Map(Some(list))
+        | Token::Push(Some(list))
+        | Token::Attribute(list) = self
+        {
+            for token in list {
+                token.walk_with(f);
+            }
         }
     }
 
-    fn scan_boolean(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('#')?;
-        let token = {
-            match self.next_if(|(_, c)| *c == 't' || *c == 'f')? {
-                (_, 't') => Token::Boolean(true),
-                (_, 'f') => Token::Boolean(false),
-                _ => return Some(Err(Error::Boolean)),
+    // array, set, push按下标取元素
+    pub fn get(&self, index: usize) -> Option<&Self> {
+        match self {
+            Token::Array(Some(list)) | Token::Set(Some(list)) | Token::Push(Some(list)) => {
+                list.get(index)
             }
+            _ => None,
+        }
+    }
+
+    // RESP2下HGETALL之类返回扁平数组, RESP3返回map, 统一成键值对
+    pub fn as_pairs(&self) -> Option<Vec<(&Self, &Self)>> {
+        let list = match self {
+            Token::Map(Some(list)) | Token::Array(Some(list)) | Token::Attribute(list) => list,
+            _ => return None,
         };
-        self.skip_line()?;
-        Some(Ok(token))
+        if list.len() % 2 != 0 {
+            return None;
+        }
+        Some(
+            list.chunks_exact(2)
+                .map(|pair| (&pair[0], &pair[1]))
+                .collect(),
+        )
     }
 
-    fn scan_set(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('~')?;
-        let count_result = self.get_integer()?;
-        self.skip_line()?;
+    // 用于日志和错误信息
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Token::SimpleString(_) => "simple_string",
+            Token::Error(_) => "error",
+            Token::Integer(_) => "integer",
+            Token::BulkString(_) => "bulk_string",
+            Token::Array(_) => "array",
+            Token::Boolean(_) => "boolean",
+            Token::Set(_) => "set",
+            Token::Double(_) => "double",
+            Token::BigNumber(_) => "big_number",
+            Token::BigErr(_) => "big_error",
+            Token::VerbatimString(_, _) => "verbatim_string",
+            Token::Map(_) => "map",
+            Token::Push(_) => "push",
+            Token::ArrayHeader(_) => "array_header",
+            Token::Null => "null",
+            Token::StreamedString(_) => "streamed_string",
+            Token::Attribute(_) => "attribute",
+        }
+    }
 
-        let mut set = Vec::new();
-        match self.get_collections(count_result, |token| {
-            set.push(token);
-        }) {
-            None => None,
-            Some(Ok(count)) if count >= 0 => Some(Ok(Token::Set(Some(set)))),
-            Some(Ok(_)) => Some(Ok(Token::Set(None))),
-            Some(Err(e)) => Some(Err(e)),
+    // streaming模式下的ArrayHeader和StreamedString分别归为Array和BulkString
+    pub fn resp_type(&self) -> RespType {
+        match self {
+            Token::SimpleString(_) => RespType::SimpleString,
+            Token::Error(_) => RespType::Error,
+            Token::Integer(_) => RespType::Integer,
+            Token::BulkString(_) | Token::StreamedString(_) => RespType::BulkString,
+            Token::Array(_) | Token::ArrayHeader(_) => RespType::Array,
+            Token::Boolean(_) => RespType::Boolean,
+            Token::Set(_) => RespType::Set,
+            Token::Double(_) => RespType::Double,
+            Token::BigNumber(_) => RespType::BigNumber,
+            Token::BigErr(_) => RespType::BigError,
+            Token::VerbatimString(_, _) => RespType::VerbatimString,
+            Token::Map(_) => RespType::Map,
+            Token::Push(_) => RespType::Push,
+            Token::Null => RespType::Null,
+            Token::Attribute(_) => RespType::Attribute,
         }
     }
 
-    fn scan_double(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token(',')?;
-        let start_position = self.get_symbol_position();
-        let mut end_position = start_position;
-        let (_, position) = self.scan_number();
-        end_position = position;
+    // 判断token的类型, 和type_name一一对应
+    pub fn is_simple_string(&self) -> bool {
+        matches!(self, Token::SimpleString(_))
+    }
 
-        if self.next_if(|(_, c)| *c == '.').is_some() {
-            let (_, position) = self.scan_number();
-            end_position = position;
-        }
+    pub fn is_error(&self) -> bool {
+        matches!(self, Token::Error(_))
+    }
 
-        if self.next_if(|(_, c)| *c == 'e' || *c == 'E').is_some() {
-            self.get_symbol_position();
-            let (_, position) = self.scan_number();
-            end_position = position;
-        }
-        let text = self.inner.get(start_position..=end_position)?;
-        self.skip_line()?;
-        Some(Ok(Token::Double(text)))
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Token::Integer(_))
     }
 
-    fn scan_big_number(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('(')?;
-        let start_position = self.get_symbol_position();
-        let (_, end_position) = self.scan_number();
-        let text = self.inner.get(start_position..=end_position)?;
-        self.skip_line()?;
-        Some(Ok(Token::BigNumber(text)))
+    pub fn is_bulk_string(&self) -> bool {
+        matches!(self, Token::BulkString(_))
     }
 
-    fn scan_big_error(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('!')?;
-        let text = self.scan_string(|(_, c)| *c != '\r' && *c != '\n')?;
-        self.skip_line()?;
-        Some(Ok(Token::BigErr(text)))
+    pub fn is_array(&self) -> bool {
+        matches!(self, Token::Array(_))
     }
 
-    fn scan_verbatim_string(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('=')?;
-        let len = self.get_integer()?;
-        self.skip_line()?;
+    pub fn is_boolean(&self) -> bool {
+        matches!(self, Token::Boolean(_))
+    }
 
-        let len = len.ok()? as usize;
+    pub fn is_set(&self) -> bool {
+        matches!(self, Token::Set(_))
+    }
 
-        let start_position = self.position;
-        dbg!(&start_position);
-        let formatter = self.scan_string(|(position, _)| *position < start_position + 3)?;
-        self.next_if(|(_, c)| *c == ':')?;
-        let text = self.scan_string(|(position, _)| *position < len + start_position)?;
-        self.skip_line()?;
+    pub fn is_double(&self) -> bool {
+        matches!(self, Token::Double(_))
+    }
 
-        Some(Ok(Token::VerbatimString(formatter, text)))
+    pub fn is_big_number(&self) -> bool {
+        matches!(self, Token::BigNumber(_))
     }
 
-    fn scan_map(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('%')?;
-        let count_result = self.get_integer()?.map(|count| count * 2);
-        self.skip_line()?;
+    pub fn is_big_error(&self) -> bool {
+        matches!(self, Token::BigErr(_))
+    }
 
-        let mut map = Vec::new();
+    pub fn is_verbatim_string(&self) -> bool {
+        matches!(self, Token::VerbatimString(_, _))
+    }
 
-        match self.get_collections(count_result, |token| {
-            map.push(token);
-        }) {
-            None => None,
-            Some(Ok(count)) if count >= 0 => Some(Ok(Token::Map(Some(map)))),
-            Some(Ok(_)) => Some(Ok(Token::Map(None))),
-            Some(Err(e)) => Some(Err(e)),
-        }
+    pub fn is_map(&self) -> bool {
+        matches!(self, Token::Map(_))
     }
 
-    fn scan_push(&mut self) -> Option<ParseResult<Token<'a>>> {
-        self.scan_token('>')?;
-        let count_result = self.get_integer()?;
-        self.skip_line()?;
+    pub fn is_push(&self) -> bool {
+        matches!(self, Token::Push(_))
+    }
 
-        let mut push = Vec::new();
-        match self.get_collections(count_result, |token| {
-            push.push(token);
-        }) {
-            None => None,
-            Some(Ok(count)) if count >= 0 => Some(Ok(Token::Push(Some(push)))),
-            Some(Ok(_)) => Some(Ok(Token::Push(None))),
-            Some(Err(e)) => Some(Err(e)),
-        }
+    pub fn is_array_header(&self) -> bool {
+        matches!(self, Token::ArrayHeader(_))
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Token::Null)
+    }
+
+    pub fn is_streamed_string(&self) -> bool {
+        matches!(self, Token::StreamedString(_))
+    }
+
+    pub fn is_attribute(&self) -> bool {
+        matches!(self, Token::Attribute(_))
     }
 }
 
-impl<'a> Iterator for Lexer<'a> {
-    type Item = ParseResult<Token<'a>>;
+impl<S: ?Sized + Input> Token<'_, S> {
+    // 在map里查找字符串类型的key
+    pub fn get_by_key(&self, key: &str) -> Option<&Self> {
+        let map = match self {
+            Token::Map(Some(map)) => map,
+            _ => return None,
+        };
+        map.chunks_exact(2)
+            .find(|pair| match &pair[0] {
+                Token::SimpleString(text)
+                | Token::BulkString(Some(text))
+                | Token::VerbatimString(_, text) => text.as_bytes() == key.as_bytes(),
+                _ => false,
+            })
+            .map(|pair| &pair[1])
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // switch (parser->curr_location[0]) {
-        //     case '$': return parseBulk(parser, p_ctx);
-        //     case '+': return parseSimpleString(parser, p_ctx);
-        //     case '-': return parseError(parser, p_ctx);
-        //     case ':': return parseLong(parser, p_ctx);
-        //     case '*': return parseArray(parser, p_ctx);
-        //     case '~': return parseSet(parser, p_ctx);
-        //     case '%': return parseMap(parser, p_ctx);
-        //     case '#': return parseBool(parser, p_ctx);
-        //     case ',': return parseDouble(parser, p_ctx);
-        //     case '_': return parseNull(parser, p_ctx);
-        //     case '(': return parseBigNumber(parser, p_ctx);
-        //     case '=': return parseVerbatimString(parser, p_ctx);
-        //     case '|': return parseAttributes(parser, p_ctx);
-        //     default: if (parser->callbacks.error) parser->callbacks.error(p_ctx);
-        // }
-        match self.scanner.peek()? {
-            (_, '+') => self.scan_simple_string(),
-            (_, '-') => self.scan_error(),
-            (_, ':') => self.scan_integer(),
-            (_, '$') => self.scan_bulk_string(),
-            (_, '*') => self.scan_array(),
-            (_, '~') => self.scan_set(),
-            (_, ',') => self.scan_double(),
-            (_, '#') => self.scan_boolean(),
-            (_, '(') => self.scan_big_number(),
-            (_, '!') => self.scan_big_error(),
-            (_, '=') => self.scan_verbatim_string(),
-            (_, '%') => self.scan_map(),
-            (_, '>') => self.scan_push(),
-            _ => {
-                todo!()
+    // +OK, +PONG这类状态回复通常不区分大小写
+    pub fn eq_ignore_ascii_case(&self, other: &str) -> bool {
+        match self {
+            Token::SimpleString(text)
+            | Token::Error(text)
+            | Token::BulkString(Some(text))
+            | Token::BigErr(text)
+            | Token::VerbatimString(_, text) => {
+                text.as_bytes().eq_ignore_ascii_case(other.as_bytes())
             }
+            _ => false,
         }
     }
 }
 
-// redis协议解析器
-#[derive(Debug)]
-pub struct Parser<'a> {
-    buf: &'a str,
+impl<S: ?Sized> PartialEq<i64> for Token<'_, S> {
+    fn eq(&self, other: &i64) -> bool {
+        matches!(self, Token::Integer(value) if value == other)
+    }
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(buf: &'a str) -> Self {
-        Self { buf }
+impl<S: ?Sized> PartialEq<bool> for Token<'_, S> {
+    fn eq(&self, other: &bool) -> bool {
+        matches!(self, Token::Boolean(value) if value == other)
     }
+}
 
-    pub fn parse(&self) {
-        let mut lexer = Lexer::new(self.buf);
+impl PartialEq<&str> for Token<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == Some(*other)
     }
 }
 
-mod tests {
-    use super::{Lexer, Token};
+impl<S: ?Sized> TryFrom<Token<'_, S>> for i64 {
+    type Error = Error;
 
-    #[test]
-    fn test_simple_string() {
-        let mut lexer = Lexer::new("+OK\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
-        assert_eq!(lexer.next(), None);
+    fn try_from(token: Token<'_, S>) -> ParseResult<i64> {
+        match token {
+            Token::Integer(value) => Ok(value),
+            _ => Err(Error::TypeMismatch {
+                expected: "integer",
+                found: token.type_name(),
+            }),
+        }
     }
+}
 
-    #[test]
-    fn test_simple_string_2() {
-        let mut lexer = Lexer::new("+OK\r\n+OK\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
-        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
-        assert_eq!(lexer.next(), None);
-    }
+impl<S: ?Sized> TryFrom<Token<'_, S>> for bool {
+    type Error = Error;
 
-    #[test]
-    fn test_error() {
-        let mut lexer = Lexer::new("-ERR unknown command 'FOO'\r\n");
-        assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::Error("ERR unknown command 'FOO'"))
-        );
-        assert_eq!(lexer.next(), None);
+    fn try_from(token: Token<'_, S>) -> ParseResult<bool> {
+        match token {
+            Token::Boolean(value) => Ok(value),
+            _ => Err(Error::TypeMismatch {
+                expected: "boolean",
+                found: token.type_name(),
+            }),
+        }
     }
+}
 
-    #[test]
-    fn test_error_2() {
-        let mut lexer = Lexer::new("-ERR unknown command 'FOO'\r\n-10086\r\n");
-        assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::Error("ERR unknown command 'FOO'"))
-        );
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Error("10086")));
-        assert_eq!(lexer.next(), None);
+impl<'a> TryFrom<Token<'a>> for &'a str {
+    type Error = Error;
+
+    fn try_from(token: Token<'a>) -> ParseResult<&'a str> {
+        token.as_str().ok_or(Error::TypeMismatch {
+            expected: "string",
+            found: token.type_name(),
+        })
     }
+}
 
-    #[test]
-    fn test_number() {
-        let mut lexer = Lexer::new(":1000\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(1000)));
-        assert_eq!(lexer.next(), None);
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    I64(ParseIntError),
+    F64(ParseFloatError),
+    // 布尔值只能是t或者f, 带上出错的字节
+    Boolean(char),
+    // 长度超出了usize或者计算长度时溢出
+    CountOverflow(i64),
+    // -1以外的负数长度
+    InvalidLength(i64),
+    IntegerEmpty,
+    // (后面没有数字, 只有符号也算
+    BigNumberEmpty,
+    Utf8(Utf8Error),
+    // 数据不完整, 需要继续读取
+    Incomplete,
+    // 要求至少一个frame的接口拿到了空的buffer
+    EmptyInput,
+    UnknownType(char),
+    // verbatim string的格式不是3个ASCII字符, 或者后面没有冒号
+    MalformedVerbatim,
+    // bulk string的内容后面不是\r\n
+    MissingTerminator,
+    // 聚合类型嵌套超过了max_depth
+    DepthExceeded(usize),
+    // Resp2模式下出现了RESP3才有的类型
+    Resp3NotAllowed(char),
+    // 声明的长度超过了max_bulk_len
+    BulkTooLarge(usize),
+    // $EOF:后面的分隔符不是40个字节
+    MalformedEofMark,
+    // simple string和error这类单行文本超过了max_line_len
+    LineTooLong(usize),
+    // inline命令为空, 引号不成对或者转义不完整
+    MalformedInline,
+    // 数组里第index个元素不是合法的utf8
+    ElementUtf8 {
+        index: usize,
+        error: Utf8Error,
+    },
+    // token转换成具体类型时类型不匹配
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[cfg(feature = "std")]
+    Io(std::io::ErrorKind),
+}
 
-        let mut lexer = Lexer::new(":+0\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(0)));
+impl Error {
+    // 帧的边界已经无法确定, 只能断开连接
+    // 其它错误只影响一行的内容, 可以用Lexer::resync跳过之后继续解析
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Error::CountOverflow(_)
+            | Error::InvalidLength(_)
+            | Error::MalformedVerbatim
+            | Error::MissingTerminator
+            | Error::DepthExceeded(_)
+            | Error::BulkTooLarge(_)
+            | Error::MalformedEofMark
+            | Error::LineTooLong(_) => true,
+            #[cfg(feature = "std")]
+            Error::Io(_) => true,
+            _ => false,
+        }
+    }
+}
 
-        let mut lexer = Lexer::new(":-0\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(-0)));
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::I64(e) => write!(f, "invalid integer: {}", e),
+            Error::F64(e) => write!(f, "invalid double: {}", e),
+            Error::Boolean(c) => write!(f, "invalid boolean byte {:?}, expected 't' or 'f'", c),
+            Error::CountOverflow(count) => write!(f, "length {} overflows", count),
+            Error::InvalidLength(count) => write!(f, "invalid length {}", count),
+            Error::IntegerEmpty => write!(f, "integer has no digits"),
+            Error::BigNumberEmpty => write!(f, "big number has no digits"),
+            Error::Utf8(e) => write!(f, "invalid utf-8: {}", e),
+            Error::Incomplete => write!(f, "incomplete frame, more data needed"),
+            Error::EmptyInput => write!(f, "empty input"),
+            Error::UnknownType(c) => write!(f, "unknown type byte {:?}", c),
+            Error::MalformedVerbatim => write!(f, "malformed verbatim string"),
+            Error::MissingTerminator => write!(f, "payload is not followed by \\r\\n"),
+            Error::DepthExceeded(max) => write!(f, "nesting exceeds the maximum depth of {}", max),
+            Error::Resp3NotAllowed(c) => {
+                write!(f, "RESP3 type {:?} is not allowed in RESP2 mode", c)
+            }
+            Error::BulkTooLarge(len) => {
+                write!(f, "bulk length {} exceeds the configured maximum", len)
+            }
+            Error::MalformedEofMark => write!(f, "EOF mark is not {} bytes", EOF_MARK_LEN),
+            Error::LineTooLong(max) => write!(f, "line exceeds the maximum length of {}", max),
+            Error::MalformedInline => write!(f, "malformed inline command"),
+            Error::ElementUtf8 { index, error } => {
+                write!(f, "invalid utf-8 in element {}: {}", index, error)
+            }
+            Error::TypeMismatch { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            #[cfg(feature = "std")]
+            Error::Io(kind) => write!(f, "io error: {}", kind),
+        }
     }
+}
 
-    #[test]
-    fn test_number_2() {
-        let mut lexer = Lexer::new(":1000\r\n:-1000\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(1000)));
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(-1000)));
-        assert_eq!(lexer.next(), None);
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::I64(e) => Some(e),
+            Error::F64(e) => Some(e),
+            Error::Utf8(e) | Error::ElementUtf8 { error: e, .. } => Some(e),
+            _ => None,
+        }
     }
+}
 
-    #[test]
-    fn test_bulk_string() {
-        let mut lexer = Lexer::new("$5\r\nhello\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(Some("hello"))));
-        assert_eq!(lexer.next(), None);
+impl From<ParseIntError> for Error {
+    fn from(err: ParseIntError) -> Self {
+        Error::I64(err)
     }
+}
 
-    #[test]
-    fn test_bulk_string_2() {
-        let mut lexer = Lexer::new("$0\r\n\r\n");
+impl From<ParseFloatError> for Error {
+    fn from(err: ParseFloatError) -> Self {
+        Error::F64(err)
+    }
+}
+
+type ParseResult<T> = core::result::Result<T, Error>;
+
+// 没有多余的0, 无穷和NaN写成inf, -inf, nan
+pub(crate) fn format_double(value: f64) -> String {
+    if value.is_nan() {
+        String::from("nan")
+    } else if value.is_infinite() {
+        String::from(if value > 0.0 { "inf" } else { "-inf" })
+    } else {
+        value.to_string()
+    }
+}
+
+pub(crate) const EOF_MARK_LEN: usize = 40;
+
+// 32位平台上 i64 直接转 usize 会被截断
+fn collection_len(count: i64) -> ParseResult<usize> {
+    if count < 0 {
+        return Err(Error::InvalidLength(count));
+    }
+    usize::try_from(count).map_err(|_| Error::CountOverflow(count))
+}
+
+// RESP3没有null set和null map, null统一用`_`表示, 负数长度当作错误
+fn non_null_count(count: i64) -> ParseResult<i64> {
+    if count < 0 {
+        Err(Error::InvalidLength(count))
+    } else {
+        Ok(count)
+    }
+}
+
+// 回调式解析, 聚合类型只通知开始和结束, 不会构建中间的Vec
+pub trait Visitor<'a, S: ?Sized = str> {
+    fn on_simple_string(&mut self, _text: &'a S) {}
+    fn on_error(&mut self, _text: &'a S) {}
+    fn on_integer(&mut self, _value: i64) {}
+    fn on_bulk_string(&mut self, _text: Option<&'a S>) {}
+    fn on_boolean(&mut self, _value: bool) {}
+    fn on_double(&mut self, _text: &'a str) {}
+    fn on_big_number(&mut self, _text: &'a str) {}
+    fn on_big_error(&mut self, _text: &'a S) {}
+    fn on_verbatim_string(&mut self, _format: &'a S, _text: &'a S) {}
+    fn on_null(&mut self) {}
+    fn on_streamed_string(&mut self, _chunks: &[&'a S]) {}
+    fn on_array_start(&mut self, _len: Option<usize>) {}
+    fn on_array_end(&mut self) {}
+    fn on_set_start(&mut self, _len: Option<usize>) {}
+    fn on_set_end(&mut self) {}
+    fn on_map_start(&mut self, _len: Option<usize>) {}
+    fn on_map_end(&mut self) {}
+    fn on_push_start(&mut self, _len: Option<usize>) {}
+    fn on_push_end(&mut self) {}
+    // 长度为?的聚合类型, kind是类型字符, 结束时才知道len, map的len是键值对的数量
+    fn on_streamed_aggregate_start(&mut self, _kind: char) {}
+    fn on_streamed_aggregate_end(&mut self, _kind: char, _len: usize) {}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ParseOptions {
+    // 数组不再一次性构建Vec, 而是先返回ArrayHeader, 再逐个返回元素
+    pub streaming: bool,
+    // RESP3下把RESP2的 $-1 和 *-1 统一转换成Null
+    pub resp3_normalize_nulls: bool,
+    // 字节lexer解析simple string和error时检查是否为合法的utf8
+    pub strict_utf8: bool,
+    // 聚合类型最多嵌套的层数, None表示不限制
+    pub max_depth: Option<usize>,
+    // bulk string之类带长度前缀的类型允许的最大字节数
+    pub max_bulk_len: Option<usize>,
+    // 跳过frame之间多余的空格, tab和换行(包括保活用的空行), 默认严格按协议解析
+    pub skip_whitespace_between_frames: bool,
+    // Resp2下遇到RESP3才有的类型直接报错
+    pub protocol: Protocol,
+    // 超出i64范围的:整数保留原始文本, 当作BigNumber返回, 默认报I64错误
+    pub integer_overflow_as_big_number: bool,
+    // simple string和error这类没有长度前缀的单行文本允许的最大字节数
+    pub max_line_len: Option<usize>,
+    // 最外层的attribute作为Token::Attribute返回, 默认直接跳过
+    pub keep_attributes: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Resp2,
+    #[default]
+    Resp3,
+}
+
+// 只有类型没有数据, 按类型分发时不用匹配token里的内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RespType {
+    SimpleString,
+    Error,
+    Integer,
+    BulkString,
+    Array,
+    Set,
+    Map,
+    Boolean,
+    Double,
+    BigNumber,
+    BigError,
+    VerbatimString,
+    Null,
+    Push,
+    Attribute,
+}
+
+impl RespType {
+    // 根据frame的第一个字节判断类型
+    pub fn from_byte(b: u8) -> Option<Self> {
+        let resp_type = match b {
+            b'+' => RespType::SimpleString,
+            b'-' => RespType::Error,
+            b':' => RespType::Integer,
+            b'$' => RespType::BulkString,
+            b'*' => RespType::Array,
+            b'~' => RespType::Set,
+            b'%' => RespType::Map,
+            b'#' => RespType::Boolean,
+            b',' => RespType::Double,
+            b'(' => RespType::BigNumber,
+            b'!' => RespType::BigError,
+            b'=' => RespType::VerbatimString,
+            b'_' => RespType::Null,
+            b'>' => RespType::Push,
+            b'|' => RespType::Attribute,
+            _ => return None,
+        };
+        Some(resp_type)
+    }
+}
+
+// 行尾的换行符, \r\n或者单独的\n, 数据不够判断时返回Incomplete
+pub(crate) fn line_terminator(bytes: &[u8]) -> ParseResult<usize> {
+    match bytes {
+        [b'\r', b'\n', ..] => Ok(2),
+        [b'\n', ..] => Ok(1),
+        [] | [b'\r'] => Err(Error::Incomplete),
+        _ => Err(Error::MissingTerminator),
+    }
+}
+
+// 带长度的payload后面必须紧跟\r\n
+pub(crate) fn payload_terminator(bytes: &[u8]) -> ParseResult<usize> {
+    match bytes {
+        [b'\r', b'\n', ..] => Ok(2),
+        [] | [b'\r'] => Err(Error::Incomplete),
+        _ => Err(Error::MissingTerminator),
+    }
+}
+
+// frame之间可以跳过的空白, 不包括is_ascii_whitespace里的\x0c
+pub(crate) fn is_frame_whitespace(c: &u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\r' | b'\n')
+}
+
+// RESP3新增的类型符号
+const RESP3_TYPES: &[u8] = b"#,(!=~%_>|";
+
+// lexer按字节扫描, 可以解析&str, 也可以解析&[u8]
+pub trait Input {
+    fn as_bytes(&self) -> &[u8];
+    fn slice(&self, start: usize, end: usize) -> Option<&Self>;
+}
+
+impl Input for str {
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Option<&Self> {
+        self.get(start..end)
+    }
+}
+
+impl Input for [u8] {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Option<&Self> {
+        self.get(start..end)
+    }
+}
+
+#[derive(Debug)]
+pub struct Lexer<'a, S: ?Sized = str> {
+    inner: &'a S,
+    position: usize,
+    options: ParseOptions,
+    done: bool,
+    // 当前所在的聚合类型层数
+    depth: usize,
+}
+
+impl<S: ?Sized> Clone for Lexer<'_, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner,
+            position: self.position,
+            options: self.options,
+            done: self.done,
+            depth: self.depth,
+        }
+    }
+}
+
+impl<'a, S: ?Sized + Input> Lexer<'a, S> {
+    pub fn new(inner: &'a S) -> Self {
+        Self::with_options(inner, ParseOptions::default())
+    }
+
+    pub fn with_options(inner: &'a S, options: ParseOptions) -> Self {
+        Self {
+            inner,
+            position: 0,
+            options,
+            done: false,
+            depth: 0,
+        }
+    }
+
+    // 复用同一个lexer解析新的buffer
+    pub fn reset(&mut self, inner: &'a S) {
+        self.inner = inner;
+        self.position = 0;
+        self.done = false;
+    }
+
+    // 连接上发送HELLO 3之后, 后面的frame按RESP3解析
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.options.protocol = protocol;
+    }
+
+    // 已经消耗的字节数
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    // 同时返回这个token消耗的字节数, 方便上层推进读指针
+    pub fn next_with_len(&mut self) -> Option<(ParseResult<Token<'a, S>>, usize)> {
+        let start = self.position;
+        let result = self.next()?;
+        Some((result, self.position - start))
+    }
+
+    // 出错之后跳到下一个\r\n之后, 从下一行重新开始解析
+    // 还没有完整的一行时不移动, 返回false
+    pub fn resync(&mut self) -> bool {
+        let rest = self
+            .inner
+            .as_bytes()
+            .get(self.position..)
+            .unwrap_or_default();
+        match rest.windows(2).position(|window| window == b"\r\n") {
+            Some(offset) => {
+                self.position += offset + 2;
+                self.done = false;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 每个token带上它在buffer里的字节范围, 方便调试工具高亮
+    pub fn spanned(&mut self) -> Spanned<'_, 'a, S> {
+        Spanned { lexer: self }
+    }
+
+    // 跳过一个完整的frame, 不构建token, 返回跳过的字节数
+    pub fn skip_frame(&mut self) -> ParseResult<usize> {
+        let start = self.position;
+        match self.visit(&mut Discard) {
+            Some(result) => result.map(|()| self.position - start),
+            None => Err(Error::Incomplete),
+        }
+    }
+
+    // 把attribute和它修饰的token配对返回, 会打开keep_attributes
+    pub fn with_attributes(&mut self) -> WithAttributes<'_, 'a, S> {
+        self.options.keep_attributes = true;
+        WithAttributes { lexer: self }
+    }
+
+    // 同时返回frame的原始数据, 代理可以不经过重新编码直接转发
+    // :+007\r\n 这类写法在Token::Integer里会丢失, 原始数据里仍然保留
+    pub fn next_raw(&mut self) -> Option<(ParseResult<Token<'a, S>>, &'a S)> {
+        let (result, span) = self.spanned().next()?;
+        let raw = self.inner.slice(span.start, span.end)?;
+        Some((result, raw))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.inner.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.next_if(|(_, c)| is_frame_whitespace(c)).is_some() {}
+    }
+
+    // 和未知类型一样跳过这个字节, 保证迭代可以继续往前走
+    fn check_protocol(&mut self, c: u8) -> ParseResult<()> {
+        if self.options.protocol == Protocol::Resp2 && RESP3_TYPES.contains(&c) {
+            self.position += 1;
+            return Err(Error::Resp3NotAllowed(c as char));
+        }
+        Ok(())
+    }
+
+    // 下一个frame的类型字节, 不会消耗输入
+    pub fn peek_type(&self) -> Option<char> {
+        if self.done {
+            return None;
+        }
+        // 和next一样先跳过frame之间的空白, 但不消耗
+        let rest = self.inner.as_bytes().get(self.position..)?;
+        let skip = self.options.skip_whitespace_between_frames && self.depth == 0;
+        rest.iter()
+            .find(|c| !skip || !is_frame_whitespace(c))
+            .map(|c| char::from(*c))
+    }
+
+    // 数字, 符号这类一定是ascii的文本
+    fn ascii(&self, start: usize, end: usize) -> Option<&'a str> {
+        let bytes = self.inner.as_bytes().get(start..end)?;
+        core::str::from_utf8(bytes).ok()
+    }
+
+    // 消耗可选的\r和必须的\n, 换行符不存在时返回None
+    fn skip_line(&mut self) -> Option<()> {
+        self.end_line().ok()
+    }
+
+    // 和skip_line一样, 但数据在换行符之前截断时返回Incomplete, 换行符不对时返回MissingTerminator
+    fn end_line(&mut self) -> ParseResult<()> {
+        let bytes = self
+            .inner
+            .as_bytes()
+            .get(self.position..)
+            .unwrap_or_default();
+        self.position += line_terminator(bytes)?;
+        Ok(())
+    }
+
+    // 类型符号之后的数据, 读到结尾说明frame不完整
+    fn peek_more(&self) -> ParseResult<u8> {
+        self.peek().ok_or(Error::Incomplete)
+    }
+
+    fn scan_token(&mut self, target_char: u8) -> Option<(usize, u8)> {
+        self.next_if(|(_, c)| *c == target_char)
+    }
+
+    fn next_if<F>(&mut self, condition: F) -> Option<(usize, u8)>
+    where
+        F: FnOnce(&(usize, u8)) -> bool,
+    {
+        let c = (self.position, self.peek()?);
+        if condition(&c) {
+            self.position += 1;
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    fn scan_string<F>(&mut self, condition: F) -> Option<&'a S>
+    where
+        F: FnOnce(&(usize, u8)) -> bool + Copy,
+    {
+        let start_position = self.position;
+        while self.next_if(condition).is_some() {}
+        self.inner.slice(start_position, self.position)
+    }
+
+    fn get_symbol_position(&mut self) -> usize {
+        self.next_if(|(_, c)| *c == b'+' || *c == b'-')
+            .unwrap_or((self.position, b'+'))
+            .0
+    }
+
+    // 没有扫描到数字时返回None
+    fn scan_number(&mut self) -> Option<(usize, usize)> {
+        let start_position = self.position;
+        let mut end_position = None;
+        while let Some((position, _)) = self.next_if(|(_, c)| c.is_ascii_digit()) {
+            end_position = Some(position);
+        }
+        end_position.map(|end_position| (start_position, end_position))
+    }
+
+    fn get_integer(&mut self) -> Option<ParseResult<i64>> {
+        let symbol_position = self.get_symbol_position();
+        let (_, end_position) = match self.scan_number() {
+            Some(range) => range,
+            None => {
+                if let Err(e) = self.peek_more() {
+                    return Some(Err(e));
+                }
+                return Some(Err(Error::IntegerEmpty));
+            }
+        };
+        let text = self.ascii(symbol_position, end_position + 1)?;
+        Some(i64::from_str(text).map_err(Error::I64))
+    }
+
+    // 类型符号加一个整数的行, 比如 :1000\r\n 和 *2\r\n
+    // 整数本身有问题时直接返回错误, 不再要求后面的换行符
+    fn scan_header(&mut self, target_char: u8) -> Option<ParseResult<i64>> {
+        self.scan_token(target_char)?;
+        let count = match self.get_integer()? {
+            Ok(count) => count,
+            Err(e) => return Some(Err(e)),
+        };
+        if let Err(e) = self.end_line() {
+            return Some(Err(e));
+        }
+        Some(Ok(count))
+    }
+
+    // 聚合类型里的元素, 嵌套层数超过max_depth时报错
+    fn nested<T, F>(&mut self, parse: F) -> Option<ParseResult<T>>
+    where
+        F: FnOnce(&mut Self) -> Option<ParseResult<T>>,
+    {
+        if let Some(max_depth) = self.options.max_depth {
+            if self.depth >= max_depth {
+                return Some(Err(Error::DepthExceeded(max_depth)));
+            }
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn scan_element(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        self.nested(Self::scan_next)
+    }
+
+    fn get_collections<F>(
+        &mut self,
+        count_result: ParseResult<i64>,
+        mut call_back: F,
+    ) -> Option<ParseResult<i64>>
+    where
+        F: FnMut(Token<'a, S>),
+    {
+        match count_result {
+            Err(e) => Some(Err(e)),
+            Ok(count) => {
+                if count >= 0 {
+                    let tmp_count = match collection_len(count) {
+                        Ok(tmp_count) => tmp_count,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    for _ in 0..tmp_count {
+                        // 元素数量还没够数据就没了, 和整个buffer为空区分开
+                        match self.scan_element() {
+                            Some(Ok(token)) => call_back(token),
+                            Some(Err(e)) => return Some(Err(e)),
+                            None => return Some(Err(Error::Incomplete)),
+                        }
+                    }
+                    Some(Ok(count))
+                } else if count == -1 {
+                    // 兼容null数组后面多出来的空行
+                    let _ = self.skip_line();
+                    Some(Ok(count))
+                } else {
+                    // 只有-1表示null, 其它负数都是非法长度
+                    Some(Err(Error::InvalidLength(count)))
+                }
+            }
+        }
+    }
+
+    // map和attribute的count是键值对的数量, 每次读取一个key和一个value
+    fn get_pairs<F>(
+        &mut self,
+        count_result: ParseResult<i64>,
+        mut call_back: F,
+    ) -> Option<ParseResult<usize>>
+    where
+        F: FnMut(Token<'a, S>, Token<'a, S>),
+    {
+        let count = match count_result
+            .and_then(non_null_count)
+            .and_then(collection_len)
+        {
+            Ok(count) => count,
+            Err(e) => return Some(Err(e)),
+        };
+        for _ in 0..count {
+            let key = match self.scan_element() {
+                Some(Ok(token)) => token,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return Some(Err(Error::Incomplete)),
+            };
+            let value = match self.scan_element() {
+                Some(Ok(token)) => token,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return Some(Err(Error::Incomplete)),
+            };
+            call_back(key, value);
+        }
+        Some(Ok(count))
+    }
+
+    // 单行的文本, 不允许出现\r\n
+    fn scan_line_text(&mut self) -> Option<ParseResult<&'a S>> {
+        let start_position = self.position;
+        while self.next_if(|(_, c)| *c != b'\r' && *c != b'\n').is_some() {
+            // 一直没有换行符时不用扫描完整个buffer
+            if let Some(max_line_len) = self.options.max_line_len {
+                if self.position - start_position > max_line_len {
+                    return Some(Err(Error::LineTooLong(max_line_len)));
+                }
+            }
+        }
+        let text = self.inner.slice(start_position, self.position)?;
+        if let Err(e) = self.end_line() {
+            return Some(Err(e));
+        }
+        if self.options.strict_utf8 {
+            if let Err(e) = core::str::from_utf8(text.as_bytes()) {
+                return Some(Err(Error::Utf8(e)));
+            }
+        }
+        Some(Ok(text))
+    }
+
+    fn scan_simple_string(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        self.scan_token(b'+')?;
+        Some(self.scan_line_text()?.map(Token::SimpleString))
+    }
+
+    fn scan_error(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        self.scan_token(b'-')?;
+        Some(self.scan_line_text()?.map(Token::Error))
+    }
+
+    fn scan_integer(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        let start = self.position + 1;
+        match self.scan_header(b':')? {
+            Ok(value) => Some(Ok(Token::Integer(value))),
+            Err(Error::I64(e))
+                if self.options.integer_overflow_as_big_number
+                    && matches!(
+                        e.kind(),
+                        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow
+                    ) =>
+            {
+                let text = self.ascii(start, self.position)?;
+                if let Err(e) = self.end_line() {
+                    return Some(Err(e));
+                }
+                Some(Ok(Token::BigNumber(text)))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    // 只看声明的长度, 不用等数据到齐就能拒绝
+    fn check_bulk_len(&self, len: usize) -> ParseResult<usize> {
+        match self.options.max_bulk_len {
+            Some(max_bulk_len) if len > max_bulk_len => Err(Error::BulkTooLarge(len)),
+            _ => Ok(len),
+        }
+    }
+
+    // 按声明的长度读取payload和后面的\r\n
+    fn scan_payload(&mut self, count: i64) -> Option<ParseResult<&'a S>> {
+        let count = match collection_len(count).and_then(|count| self.check_bulk_len(count)) {
+            Ok(count) => count,
+            Err(e) => return Some(Err(e)),
+        };
+        let end_position = self.position.saturating_add(count);
+        // payload比声明的长度短, 后面的\r\n也可能是payload的一部分
+        if end_position > self.inner.as_bytes().len() {
+            return Some(Err(Error::Incomplete));
+        }
+        // 直接按长度切片, 不用逐字节判断
+        let text = match self.slice_payload(self.position, end_position) {
+            Ok(text) => text,
+            Err(e) => return Some(Err(e)),
+        };
+        self.position = end_position;
+        Some(self.end_payload().map(|()| text))
+    }
+
+    // 只有&str在字符中间切开时才会失败
+    fn slice_payload(&self, start: usize, end: usize) -> ParseResult<&'a S> {
+        self.inner.slice(start, end).ok_or_else(|| {
+            let bytes = &self.inner.as_bytes()[start..end];
+            core::str::from_utf8(bytes)
+                .err()
+                .map_or(Error::Incomplete, Error::Utf8)
+        })
+    }
+
+    // 按长度读取的payload后面必须是\r\n, 单独的\n可能是payload的一部分
+    fn end_payload(&mut self) -> ParseResult<()> {
+        let bytes = self
+            .inner
+            .as_bytes()
+            .get(self.position..)
+            .unwrap_or_default();
+        self.position += payload_terminator(bytes)?;
+        Ok(())
+    }
+
+    // $?\r\n;4\r\nHell\r\n;1\r\no\r\n;0\r\n
+    fn scan_streamed_string(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        self.position += 2;
+        if let Err(e) = self.end_line() {
+            return Some(Err(e));
+        }
+        let mut chunks = Vec::new();
+        loop {
+            match self.peek() {
+                Some(b';') => {}
+                Some(c) => return Some(Err(Error::UnknownType(c as char))),
+                None => return Some(Err(Error::Incomplete)),
+            }
+            let count = match self.scan_header(b';')? {
+                Ok(0) => return Some(Ok(Token::StreamedString(chunks))),
+                Ok(count) => count,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.scan_payload(count)? {
+                Ok(chunk) => chunks.push(chunk),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    // 长度为?的类型, 比如 *?\r\n 和 $?\r\n
+    fn is_streamed(&self) -> bool {
+        self.inner.as_bytes().get(self.position + 1) == Some(&b'?')
+    }
+
+    // 长度未知的聚合类型, 元素一直读到 .\r\n 为止, map只能在键值对之间结束
+    fn get_streamed_collections<F>(
+        &mut self,
+        pairs: bool,
+        mut call_back: F,
+    ) -> Option<ParseResult<()>>
+    where
+        F: FnMut(Token<'a, S>),
+    {
+        self.position += 2;
+        if let Err(e) = self.end_line() {
+            return Some(Err(e));
+        }
+        let mut count = 0;
+        loop {
+            if (!pairs || count % 2 == 0) && self.peek() == Some(b'.') {
+                self.position += 1;
+                return Some(self.end_line());
+            }
+            match self.scan_element() {
+                Some(Ok(token)) => call_back(token),
+                Some(Err(e)) => return Some(Err(e)),
+                None => return Some(Err(Error::Incomplete)),
+            }
+            count += 1;
+        }
+    }
+
+    // 无盘复制时的RDB: $EOF:<40字节的分隔符>\r\n<payload><分隔符>, 结尾没有\r\n
+    fn scan_eof_marked_string(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        let bytes = self.inner.as_bytes();
+        let mark_start = self.position + 5;
+        let mark_end = mark_start + EOF_MARK_LEN;
+        let rest = bytes.get(mark_start..)?;
+        match rest.iter().position(|c| *c == b'\r' || *c == b'\n') {
+            Some(len) if len == EOF_MARK_LEN => {}
+            None if rest.len() < EOF_MARK_LEN + 2 => return Some(Err(Error::Incomplete)),
+            _ => return Some(Err(Error::MalformedEofMark)),
+        }
+        let mark = &bytes[mark_start..mark_end];
+        self.position = mark_end;
+        if let Err(e) = self.end_line() {
+            return Some(Err(e));
+        }
+        let start_position = self.position;
+        // 没有声明长度, 只在max_bulk_len + 分隔符的范围里找分隔符
+        let rest = &bytes[start_position..];
+        let window = match self.options.max_bulk_len {
+            Some(max_bulk_len) => &rest[..rest.len().min(max_bulk_len + EOF_MARK_LEN)],
+            None => rest,
+        };
+        let len = match window.windows(EOF_MARK_LEN).position(|w| w == mark) {
+            Some(len) => len,
+            None => match self.options.max_bulk_len {
+                Some(max_bulk_len) if window.len() == max_bulk_len + EOF_MARK_LEN => {
+                    return Some(Err(Error::BulkTooLarge(max_bulk_len + 1)));
+                }
+                _ => return Some(Err(Error::Incomplete)),
+            },
+        };
+        let text = self.inner.slice(start_position, start_position + len)?;
+        self.position = start_position + len + EOF_MARK_LEN;
+        Some(Ok(Token::BulkString(Some(text))))
+    }
+
+    fn scan_bulk_string(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        if self.is_streamed() {
+            return self.scan_streamed_string();
+        }
+        let rest = &self.inner.as_bytes()[self.position..];
+        if rest.starts_with(b"$EOF:") {
+            return self.scan_eof_marked_string();
+        }
+        // $E, $EO和$EOF之后可能还是EOF标记, 要等后面的数据
+        if rest.len() > 1 && b"$EOF:".starts_with(rest) {
+            return Some(Err(Error::Incomplete));
+        }
+        let count_result = self.scan_header(b'$')?;
+
+        match count_result {
+            Ok(count) => {
+                if count >= 0 {
+                    let text = match self.scan_payload(count)? {
+                        Ok(text) => text,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    Some(Ok(Token::BulkString(Some(text))))
+                } else if count != -1 {
+                    Some(Err(Error::InvalidLength(count)))
+                } else if self.options.resp3_normalize_nulls {
+                    Some(Ok(Token::Null))
+                } else {
+                    Some(Ok(Token::BulkString(None)))
+                }
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn scan_array(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        if self.is_streamed() {
+            let mut list = Vec::new();
+            let result = self.get_streamed_collections(false, |token| list.push(token))?;
+            return Some(result.map(|()| Token::Array(Some(list))));
+        }
+        let count_result = self.scan_header(b'*')?;
+
+        // map, set和push里的数组仍然一次性构建, 否则元素会被当成外层的frame返回
+        if self.options.streaming && self.depth == 0 {
+            if let Ok(count) = count_result {
+                if count >= 0 {
+                    return Some(collection_len(count).map(Token::ArrayHeader));
+                }
+            }
+        }
+
+        let mut list = Vec::new();
+        match self.get_collections(count_result, |token| list.push(token)) {
+            None => None,
+            Some(Ok(count)) if count >= 0 => Some(Ok(Token::Array(Some(list)))),
+            Some(Ok(_)) if self.options.resp3_normalize_nulls => Some(Ok(Token::Null)),
+            Some(Ok(_)) => Some(Ok(Token::Array(None))),
+            Some(Err(e)) => Some(Err(e)),
+        }
+    }
+
+    fn scan_boolean(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        self.scan_token(b'#')?;
+        let value = match self.peek_more() {
+            Ok(b't') => true,
+            Ok(b'f') => false,
+            // 没有值的 #\r\n 和原来一样不返回token
+            Ok(b'\r' | b'\n') => return None,
+            Ok(c) => return Some(Err(Error::Boolean(c as char))),
+            Err(e) => return Some(Err(e)),
+        };
+        self.position += 1;
+        // t/f后面只能是换行
+        match self.peek() {
+            Some(b'\r' | b'\n') | None => {}
+            Some(c) => return Some(Err(Error::Boolean(c as char))),
+        }
+        Some(self.end_line().map(|()| Token::Boolean(value)))
+    }
+
+    fn scan_set(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        if self.is_streamed() {
+            let mut list = Vec::new();
+            let result = self.get_streamed_collections(false, |token| list.push(token))?;
+            return Some(result.map(|()| Token::Set(Some(list))));
+        }
+        let count_result = self.scan_header(b'~')?.and_then(non_null_count);
+
+        let mut set = Vec::new();
+        match self.get_collections(count_result, |token| {
+            set.push(token);
+        }) {
+            None => None,
+            Some(Ok(_)) => Some(Ok(Token::Set(Some(set)))),
+            Some(Err(e)) => Some(Err(e)),
+        }
+    }
+
+    // 支持 3.14, -3.14, .5, 5., 2, +1.5e-3 这些形式
+    fn scan_double(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        self.scan_token(b',')?;
+        let start_position = self.position;
+        self.get_symbol_position();
+        self.scan_number();
+
+        if self.next_if(|(_, c)| *c == b'.').is_some() {
+            self.scan_number();
+        }
+
+        // 指数的符号也包含在start_position到当前位置的文本里
+        if self.next_if(|(_, c)| *c == b'e' || *c == b'E').is_some() {
+            self.get_symbol_position();
+            self.scan_number();
+        }
+
+        // 数据还没读完, 不能判断格式是否正确
+        if let Err(e) = self.peek_more() {
+            return Some(Err(e));
+        }
+        let text = self.ascii(start_position, self.position)?;
+        if let Err(e) = f64::from_str(text) {
+            return Some(Err(Error::F64(e)));
+        }
+        Some(self.end_line().map(|()| Token::Double(text)))
+    }
+
+    fn scan_null(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        self.scan_token(b'_')?;
+        Some(self.end_line().map(|()| Token::Null))
+    }
+
+    fn scan_big_number(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        self.scan_token(b'(')?;
+        let start_position = self.get_symbol_position();
+        let (_, end_position) = match self.scan_number() {
+            Some(range) => range,
+            None => {
+                if let Err(e) = self.peek_more() {
+                    return Some(Err(e));
+                }
+                return Some(Err(Error::BigNumberEmpty));
+            }
+        };
+        let text = self.ascii(start_position, end_position + 1)?;
+        Some(self.end_line().map(|()| Token::BigNumber(text)))
+    }
+
+    fn scan_big_error(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        // RESP3的blob error带有长度, 同时兼容单行的写法
+        // 整行都是数字才当成长度, !404 Not Found这种单行错误不能按长度解析
+        let rest = self
+            .inner
+            .as_bytes()
+            .get(self.position + 1..)
+            .unwrap_or_default();
+        let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+        let length_prefixed = match rest.get(digits) {
+            None => return Some(Err(Error::Incomplete)),
+            Some(b'\r' | b'\n') => digits > 0,
+            Some(_) => false,
+        };
+        if length_prefixed {
+            let count = match self.scan_header(b'!')? {
+                Ok(count) => count,
+                Err(e) => return Some(Err(e)),
+            };
+            return Some(self.scan_payload(count)?.map(Token::BigErr));
+        }
+        self.scan_token(b'!')?;
+        Some(self.scan_line_text()?.map(Token::BigErr))
+    }
+
+    fn scan_verbatim_string(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        let len = match self
+            .scan_header(b'=')?
+            .and_then(collection_len)
+            .and_then(|len| self.check_bulk_len(len))
+        {
+            Ok(len) => len,
+            Err(e) => return Some(Err(e)),
+        };
+
+        // 长度来自对端, 不能直接相加
+        let start_position = self.position;
+        let end_position = match start_position.checked_add(len) {
+            Some(end_position) => end_position,
+            None => return Some(Err(Error::CountOverflow(len as i64))),
+        };
+        if end_position > self.inner.as_bytes().len() {
+            return Some(Err(Error::Incomplete));
+        }
+        // 至少要有3个字节的格式和一个冒号
+        if len < 4 {
+            return Some(Err(Error::MalformedVerbatim));
+        }
+        let formatter_end = start_position + 3;
+        let formatter = self
+            .scan_string(|(position, c)| *position < formatter_end && c.is_ascii_alphanumeric())?;
+        if self.position != formatter_end {
+            return Some(Err(Error::MalformedVerbatim));
+        }
+        if self.next_if(|(_, c)| *c == b':').is_none() {
+            return Some(Err(Error::MalformedVerbatim));
+        }
+        let text = match self.slice_payload(self.position, end_position) {
+            Ok(text) => text,
+            Err(e) => return Some(Err(e)),
+        };
+        self.position = end_position;
+        Some(
+            self.end_payload()
+                .map(|()| Token::VerbatimString(formatter, text)),
+        )
+    }
+
+    fn scan_map(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        if self.is_streamed() {
+            let mut list = Vec::new();
+            let result = self.get_streamed_collections(true, |token| list.push(token))?;
+            return Some(result.map(|()| Token::Map(Some(list))));
+        }
+        let count_result = self.scan_header(b'%')?;
+
+        let mut map = Vec::new();
+
+        match self.get_pairs(count_result, |key, value| {
+            map.push(key);
+            map.push(value);
+        }) {
+            None => None,
+            Some(Ok(_)) => Some(Ok(Token::Map(Some(map)))),
+            Some(Err(e)) => Some(Err(e)),
+        }
+    }
+
+    fn scan_push(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        if self.is_streamed() {
+            let mut list = Vec::new();
+            let result = self.get_streamed_collections(false, |token| list.push(token))?;
+            return Some(result.map(|()| Token::Push(Some(list))));
+        }
+        let count_result = self.scan_header(b'>')?;
+
+        let mut push = Vec::new();
+        match self.get_collections(count_result, |token| {
+            push.push(token);
+        }) {
+            None => None,
+            Some(Ok(count)) if count >= 0 => Some(Ok(Token::Push(Some(push)))),
+            Some(Ok(_)) => Some(Ok(Token::Push(None))),
+            Some(Err(e)) => Some(Err(e)),
+        }
+    }
+
+    // attribute是附加在下一个reply上的map, 目前直接跳过
+    fn skip_attribute(&mut self) -> Option<ParseResult<()>> {
+        let count_result = self.scan_header(b'|')?;
+
+        match self.get_pairs(count_result, |_, _| {})? {
+            Ok(_) => Some(Ok(())),
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn scan_attribute(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        // 聚合类型里的attribute不占元素个数, 只有最外层的才单独返回
+        if self.options.keep_attributes && self.depth == 0 {
+            let count_result = self.scan_header(b'|')?;
+            let mut list = Vec::new();
+            return match self.get_pairs(count_result, |key, value| {
+                list.push(key);
+                list.push(value);
+            })? {
+                Ok(_) => Some(Ok(Token::Attribute(list))),
+                Err(e) => Some(Err(e)),
+            };
+        }
+        if let Err(e) = self.skip_attribute()? {
+            return Some(Err(e));
+        }
+        self.scan_next()
+    }
+
+    pub fn visit<V>(&mut self, visitor: &mut V) -> Option<ParseResult<()>>
+    where
+        V: Visitor<'a, S>,
+    {
+        if self.options.skip_whitespace_between_frames && self.depth == 0 {
+            self.skip_whitespace();
+        }
+        let c = self.peek()?;
+        if let Err(e) = self.check_protocol(c) {
+            return Some(Err(e));
+        }
+        match c {
+            b'|' => {
+                if let Err(e) = self.skip_attribute()? {
+                    return Some(Err(e));
+                }
+                return self.visit(visitor);
+            }
+            b'*' => return self.visit_aggregate(b'*', visitor),
+            b'~' => return self.visit_aggregate(b'~', visitor),
+            b'%' => return self.visit_aggregate(b'%', visitor),
+            b'>' => return self.visit_aggregate(b'>', visitor),
+            _ => {}
+        }
+
+        let token = match self.next()? {
+            Ok(token) => token,
+            Err(e) => return Some(Err(e)),
+        };
+        match token {
+            Token::SimpleString(text) => visitor.on_simple_string(text),
+            Token::Error(text) => visitor.on_error(text),
+            Token::Integer(value) => visitor.on_integer(value),
+            Token::BulkString(text) => visitor.on_bulk_string(text),
+            Token::Boolean(value) => visitor.on_boolean(value),
+            Token::Double(text) => visitor.on_double(text),
+            Token::BigNumber(text) => visitor.on_big_number(text),
+            Token::BigErr(text) => visitor.on_big_error(text),
+            Token::VerbatimString(format, text) => visitor.on_verbatim_string(format, text),
+            Token::Null => visitor.on_null(),
+            Token::StreamedString(chunks) => visitor.on_streamed_string(&chunks),
+            Token::Array(_)
+            | Token::Set(_)
+            | Token::Map(_)
+            | Token::Push(_)
+            | Token::ArrayHeader(_)
+            | Token::Attribute(_) => {
+                unreachable!("aggregates are visited through their headers")
+            }
+        }
+        Some(Ok(()))
+    }
+
+    fn visit_aggregate<V>(&mut self, target_char: u8, visitor: &mut V) -> Option<ParseResult<()>>
+    where
+        V: Visitor<'a, S>,
+    {
+        if self.is_streamed() {
+            return self.visit_streamed(target_char, visitor);
+        }
+        let count_result = self.scan_header(target_char)?;
+
+        let count = match count_result {
+            Ok(count) if target_char == b'~' || target_char == b'%' => {
+                match non_null_count(count) {
+                    Ok(count) => count,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+            Ok(count) => count,
+            Err(e) => return Some(Err(e)),
+        };
+        let len = if count >= 0 {
+            match collection_len(count) {
+                Ok(len) => Some(len),
+                Err(e) => return Some(Err(e)),
+            }
+        } else if count == -1 {
+            None
+        } else {
+            return Some(Err(Error::InvalidLength(count)));
+        };
+        if len.is_none() && target_char == b'*' && self.options.resp3_normalize_nulls {
+            let _ = self.skip_line();
+            visitor.on_null();
+            return Some(Ok(()));
+        }
+        visit_start(visitor, target_char, len);
+
+        match len {
+            Some(len) => {
+                // map的len是键值对的数量
+                let elements = if target_char == b'%' {
+                    match len.checked_mul(2) {
+                        Some(elements) => elements,
+                        None => return Some(Err(Error::CountOverflow(count))),
+                    }
+                } else {
+                    len
+                };
+                for _ in 0..elements {
+                    match self.nested(|lexer| lexer.visit(visitor)) {
+                        Some(Ok(())) => {}
+                        Some(Err(e)) => return Some(Err(e)),
+                        None => return Some(Err(Error::Incomplete)),
+                    }
+                }
+            }
+            None => {
+                let _ = self.skip_line();
+            }
+        }
+
+        visit_end(visitor, target_char);
+        Some(Ok(()))
+    }
+
+    // 长度为?的聚合类型, 只扫描一遍, 读到结尾的 .\r\n 时才通知元素个数
+    fn visit_streamed<V>(&mut self, target_char: u8, visitor: &mut V) -> Option<ParseResult<()>>
+    where
+        V: Visitor<'a, S>,
+    {
+        let pairs = target_char == b'%';
+        self.position += 2;
+        if let Err(e) = self.end_line() {
+            return Some(Err(e));
+        }
+        let kind = char::from(target_char);
+        visitor.on_streamed_aggregate_start(kind);
+
+        let mut elements = 0usize;
+        loop {
+            if (!pairs || elements.is_multiple_of(2)) && self.peek() == Some(b'.') {
+                self.position += 1;
+                if let Err(e) = self.end_line() {
+                    return Some(Err(e));
+                }
+                break;
+            }
+            match self.nested(|lexer| lexer.visit(visitor)) {
+                Some(Ok(())) => {}
+                Some(Err(e)) => return Some(Err(e)),
+                None => return Some(Err(Error::Incomplete)),
+            }
+            elements += 1;
+        }
+
+        let len = if pairs { elements / 2 } else { elements };
+        visitor.on_streamed_aggregate_end(kind, len);
+        Some(Ok(()))
+    }
+}
+
+fn visit_start<'a, S: ?Sized, V: Visitor<'a, S>>(
+    visitor: &mut V,
+    target_char: u8,
+    len: Option<usize>,
+) {
+    match target_char {
+        b'*' => visitor.on_array_start(len),
+        b'~' => visitor.on_set_start(len),
+        b'%' => visitor.on_map_start(len),
+        _ => visitor.on_push_start(len),
+    }
+}
+
+fn visit_end<'a, S: ?Sized, V: Visitor<'a, S>>(visitor: &mut V, target_char: u8) {
+    match target_char {
+        b'*' => visitor.on_array_end(),
+        b'~' => visitor.on_set_end(),
+        b'%' => visitor.on_map_end(),
+        _ => visitor.on_push_end(),
+    }
+}
+
+impl<'a, S: ?Sized + Input> Iterator for Lexer<'a, S> {
+    type Item = ParseResult<Token<'a, S>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // 返回过一次None之后就一直返回None
+        if self.done {
+            return None;
+        }
+        if self.options.skip_whitespace_between_frames && self.depth == 0 {
+            self.skip_whitespace();
+        }
+        let result = self.scan_next();
+        if result.is_none() {
+            self.done = true;
+        }
+        result
+    }
+}
+
+impl<'a, S: ?Sized + Input> FusedIterator for Lexer<'a, S> {}
+
+impl<'a, S: ?Sized + Input> Lexer<'a, S> {
+    fn scan_next(&mut self) -> Option<ParseResult<Token<'a, S>>> {
+        // switch (parser->curr_location[0]) {
+        //     case '$': return parseBulk(parser, p_ctx);
+        //     case '+': return parseSimpleString(parser, p_ctx);
+        //     case '-': return parseError(parser, p_ctx);
+        //     case ':': return parseLong(parser, p_ctx);
+        //     case '*': return parseArray(parser, p_ctx);
+        //     case '~': return parseSet(parser, p_ctx);
+        //     case '%': return parseMap(parser, p_ctx);
+        //     case '#': return parseBool(parser, p_ctx);
+        //     case ',': return parseDouble(parser, p_ctx);
+        //     case '_': return parseNull(parser, p_ctx);
+        //     case '(': return parseBigNumber(parser, p_ctx);
+        //     case '=': return parseVerbatimString(parser, p_ctx);
+        //     case '|': return parseAttributes(parser, p_ctx);
+        //     default: if (parser->callbacks.error) parser->callbacks.error(p_ctx);
+        // }
+        let c = self.peek()?;
+        if let Err(e) = self.check_protocol(c) {
+            return Some(Err(e));
+        }
+        match c {
+            b'+' => self.scan_simple_string(),
+            b'-' => self.scan_error(),
+            b':' => self.scan_integer(),
+            b'$' => self.scan_bulk_string(),
+            b'*' => self.scan_array(),
+            b'~' => self.scan_set(),
+            b',' => self.scan_double(),
+            b'#' => self.scan_boolean(),
+            b'(' => self.scan_big_number(),
+            b'!' => self.scan_big_error(),
+            b'=' => self.scan_verbatim_string(),
+            b'%' => self.scan_map(),
+            b'>' => self.scan_push(),
+            b'|' => self.scan_attribute(),
+            b'_' => self.scan_null(),
+            c => {
+                // 跳过无法识别的字节, 保证迭代可以继续往前走
+                self.position += 1;
+                Some(Err(Error::UnknownType(c as char)))
+            }
+        }
+    }
+}
+
+// 逐项设置ParseOptions, 默认值和Parser::new一致
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParserBuilder {
+    options: ParseOptions,
+}
+
+impl ParserBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn streaming(mut self, streaming: bool) -> Self {
+        self.options.streaming = streaming;
+        self
+    }
+
+    pub fn resp3_normalize_nulls(mut self, normalize: bool) -> Self {
+        self.options.resp3_normalize_nulls = normalize;
+        self
+    }
+
+    pub fn strict_utf8(mut self, strict: bool) -> Self {
+        self.options.strict_utf8 = strict;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn max_bulk_len(mut self, max_bulk_len: usize) -> Self {
+        self.options.max_bulk_len = Some(max_bulk_len);
+        self
+    }
+
+    pub fn max_line_len(mut self, max_line_len: usize) -> Self {
+        self.options.max_line_len = Some(max_line_len);
+        self
+    }
+
+    pub fn keep_attributes(mut self, keep: bool) -> Self {
+        self.options.keep_attributes = keep;
+        self
+    }
+
+    pub fn skip_whitespace_between_frames(mut self, skip: bool) -> Self {
+        self.options.skip_whitespace_between_frames = skip;
+        self
+    }
+
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.options.protocol = protocol;
+        self
+    }
+
+    pub fn integer_overflow_as_big_number(mut self, enabled: bool) -> Self {
+        self.options.integer_overflow_as_big_number = enabled;
+        self
+    }
+
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    pub fn build(self, buf: &str) -> Parser<'_> {
+        Parser::with_options(buf, self.options)
+    }
+}
+
+// 累计消耗的字节数和解析出来的frame数量
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ParseStats {
+    pub bytes: usize,
+    pub frames: usize,
+}
+
+// redis协议解析器
+#[derive(Debug, Clone)]
+pub struct Parser<'a> {
+    buf: &'a str,
+    options: ParseOptions,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(buf: &'a str) -> Self {
+        Self::with_options(buf, ParseOptions::default())
+    }
+
+    pub fn with_options(buf: &'a str, options: ParseOptions) -> Self {
+        Self { buf, options }
+    }
+
+    pub fn builder() -> ParserBuilder {
+        ParserBuilder::new()
+    }
+
+    pub fn peek_type(&self) -> Option<char> {
+        Lexer::with_options(self.buf, self.options).peek_type()
+    }
+
+    // pipeline的多个回复首尾相连, 逐个返回buffer里的frame
+    pub fn frames(&self) -> Frames<'_, 'a> {
+        Frames {
+            lexer: Lexer::with_options(self.buf, self.options),
+            stats: ParseStats::default(),
+            _parser: PhantomData,
+        }
+    }
+
+    // 严格模式, 最后一个frame之后只允许出现空白字符
+    pub fn parse_strict(&self) -> ParseResult<Vec<Token<'a>>> {
+        let mut tokens = Vec::new();
+        self.parse_into(&mut tokens)?;
+        Ok(tokens)
+    }
+
+    // 只解析第一个frame, 空buffer返回EmptyInput
+    // parse_strict对空buffer返回空的Vec, 表示成功解析了0个frame
+    pub fn parse_one(&self) -> ParseResult<Token<'a>> {
+        if self.buf.as_bytes().iter().all(is_frame_whitespace) {
+            return Err(Error::EmptyInput);
+        }
+        let mut lexer = Lexer::with_options(self.buf, self.options);
+        lexer.next().unwrap_or(Err(Error::Incomplete))
+    }
+
+    // 和parse_strict一样, 但是复用调用方的Vec, 返回解析出来的frame数量
+    pub fn parse_into(&self, tokens: &mut Vec<Token<'a>>) -> ParseResult<usize> {
+        tokens.clear();
+        let mut lexer = Lexer::with_options(self.buf, self.options);
+        loop {
+            let rest = &self.buf.as_bytes()[lexer.position()..];
+            if rest.iter().all(is_frame_whitespace) {
+                return Ok(tokens.len());
+            }
+            match lexer.next() {
+                Some(token) => tokens.push(token?),
+                None => return Err(Error::Incomplete),
+            }
+        }
+    }
+
+    pub fn parse_with_callbacks<V>(&self, visitor: &mut V) -> ParseResult<()>
+    where
+        V: Visitor<'a>,
+    {
+        let mut lexer = Lexer::with_options(self.buf, self.options);
+        while let Some(result) = lexer.visit(visitor) {
+            result?;
+        }
+        Ok(())
+    }
+
+    // 只统计完整的frame数量, 通过visit跳过元素, 不会构建token
+    pub fn count_frames(&self) -> ParseResult<usize> {
+        let mut lexer = Lexer::with_options(self.buf, self.options);
+        let mut count = 0;
+        loop {
+            let rest = &self.buf.as_bytes()[lexer.position()..];
+            if rest.iter().all(is_frame_whitespace) {
+                return Ok(count);
+            }
+            match lexer.visit(&mut Discard) {
+                Some(result) => result?,
+                None => return Err(Error::Incomplete),
+            }
+            count += 1;
+        }
+    }
+
+    // 转发之前检查整个buffer都是合法的RESP, 返回第一个错误, 末尾被截断时返回Incomplete
+    pub fn validate(&self) -> ParseResult<()> {
+        self.count_frames().map(|_| ())
+    }
+}
+
+// 忽略所有回调的visitor
+struct Discard;
+
+impl<S: ?Sized> Visitor<'_, S> for Discard {}
+
+// Parser::frames返回的迭代器, 同时统计已经迭代过的frame
+#[derive(Debug)]
+pub struct Frames<'p, 'a> {
+    lexer: Lexer<'a>,
+    stats: ParseStats,
+    _parser: PhantomData<&'p Parser<'a>>,
+}
+
+impl Frames<'_, '_> {
+    pub fn stats(&self) -> ParseStats {
+        self.stats
+    }
+}
+
+impl<'a> Iterator for Frames<'_, 'a> {
+    type Item = ParseResult<Token<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.lexer.position();
+        let result = self.lexer.next()?;
+        self.stats.bytes += self.lexer.position() - start;
+        self.stats.frames += usize::from(result.is_ok());
+        Some(result)
+    }
+}
+
+impl FusedIterator for Frames<'_, '_> {}
+
+// Lexer::spanned返回的迭代器
+#[derive(Debug)]
+pub struct Spanned<'l, 'a, S: ?Sized = str> {
+    lexer: &'l mut Lexer<'a, S>,
+}
+
+impl<'a, S: ?Sized + Input> Iterator for Spanned<'_, 'a, S> {
+    type Item = (ParseResult<Token<'a, S>>, Range<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // frame之间跳过的空白不算在范围里
+        if self.lexer.options.skip_whitespace_between_frames && !self.lexer.done {
+            self.lexer.skip_whitespace();
+        }
+        let start = self.lexer.position;
+        let result = self.lexer.next()?;
+        Some((result, start..self.lexer.position))
+    }
+}
+
+impl<S: ?Sized + Input> FusedIterator for Spanned<'_, '_, S> {}
+
+// Lexer::with_attributes返回的迭代器
+#[derive(Debug)]
+pub struct WithAttributes<'l, 'a, S: ?Sized = str> {
+    lexer: &'l mut Lexer<'a, S>,
+}
+
+impl<'a, S: ?Sized + Input> Iterator for WithAttributes<'_, 'a, S> {
+    #[allow(clippy::type_complexity)]
+    type Item = ParseResult<(Option<Vec<Token<'a, S>>>, Token<'a, S>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let attribute = match self.lexer.next()? {
+            Ok(Token::Attribute(list)) => list,
+            Ok(token) => return Some(Ok((None, token))),
+            Err(e) => return Some(Err(e)),
+        };
+        // attribute后面一定跟着被修饰的token
+        match self.lexer.next() {
+            Some(Ok(token)) => Some(Ok((Some(attribute), token))),
+            Some(Err(e)) => Some(Err(e)),
+            None => Some(Err(Error::Incomplete)),
+        }
+    }
+}
+
+impl<S: ?Sized + Input> FusedIterator for WithAttributes<'_, '_, S> {}
+
+impl<'p, 'a> IntoIterator for &'p Parser<'a> {
+    type Item = ParseResult<Token<'a>>;
+    type IntoIter = Frames<'p, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.frames()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        collection_len, Error, Lexer, ParseOptions, ParseStats, Parser, ParserBuilder, Protocol,
+        RedisError, RespType, Token, VerbatimFormat, Visitor,
+    };
+
+    #[test]
+    fn test_simple_string() {
+        let mut lexer = Lexer::new("+OK\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_simple_string_2() {
+        let mut lexer = Lexer::new("+OK\r\n+OK\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_error() {
+        let mut lexer = Lexer::new("-ERR unknown command 'FOO'\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Error("ERR unknown command 'FOO'"))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_error_2() {
+        let mut lexer = Lexer::new("-ERR unknown command 'FOO'\r\n-10086\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Error("ERR unknown command 'FOO'"))
+        );
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Error("10086")));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_number() {
+        let mut lexer = Lexer::new(":1000\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(1000)));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new(":+0\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(0)));
+
+        let mut lexer = Lexer::new(":-0\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(-0)));
+    }
+
+    #[test]
+    fn test_number_2() {
+        let mut lexer = Lexer::new(":1000\r\n:-1000\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(1000)));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(-1000)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_bulk_string() {
+        let mut lexer = Lexer::new("$5\r\nhello\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(Some("hello"))));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_bulk_string_2() {
+        let mut lexer = Lexer::new("$0\r\n\r\n");
         assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(Some(""))));
         assert_eq!(lexer.next(), None);
     }
 
     #[test]
-    fn test_bulk_string_3() {
-        let mut lexer = Lexer::new("$-1\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(None)));
+    fn test_bulk_string_3() {
+        let mut lexer = Lexer::new("$-1\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(None)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_array() {
+        let mut lexer = Lexer::new("*0\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Array(Some(vec![]))));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_array_2() {
+        let mut lexer = Lexer::new("*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![
+                Token::BulkString(Some("foo")),
+                Token::BulkString(Some("bar")),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_array_3() {
+        let mut lexer = Lexer::new("*3\r\n:1\r\n:2\r\n:3\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![
+                Token::Integer(1),
+                Token::Integer(2),
+                Token::Integer(3),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_array_4() {
+        let mut lexer = Lexer::new("*5\r\n:1\r\n:2\r\n:3\r\n:4\r\n$6\r\nfoobar\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![
+                Token::Integer(1),
+                Token::Integer(2),
+                Token::Integer(3),
+                Token::Integer(4),
+                Token::BulkString(Some("foobar")),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_array_5() {
+        let mut lexer = Lexer::new("*-1\r\n\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Array(None)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_array_null_elements() {
+        // MGET里不存在的key返回 $-1
+        let mut lexer = Lexer::new("*2\r\n$-1\r\n$3\r\nfoo\r\n*3\r\n$-1\r\n*-1\r\n$-1\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![
+                Token::BulkString(None),
+                Token::BulkString(Some("foo")),
+            ])))
+        );
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![
+                Token::BulkString(None),
+                Token::Array(None),
+                Token::BulkString(None),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_boolean() {
+        let mut lexer = Lexer::new("#t\r\n#f\r\n#\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Boolean(true)));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Boolean(false)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_double() {
+        let mut lexer = Lexer::new(",3.14\r\n,-3.14\r\n,5.9e3\r\n,2\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("3.14")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("-3.14")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("5.9e3")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("2")));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_big_number() {
+        let mut lexer = Lexer::new("(123\r\n(-123\r\n(+123\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BigNumber("123")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BigNumber("-123")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BigNumber("+123")));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_big_error() {
+        let mut lexer = Lexer::new("!OK\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BigErr("OK")));
+        assert_eq!(lexer.next(), None);
+
+        // 数字开头的单行错误
+        let mut lexer = Lexer::new("!404 Not Found\r\n!21\r\nSYNTAX invalid syntax\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BigErr("404 Not Found")));
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::BigErr("SYNTAX invalid syntax"))
+        );
+        assert_eq!(lexer.next(), None);
+
+        // 还没读到行尾时不能确定是哪种写法
+        assert_eq!(Lexer::new("!404").next(), Some(Err(Error::Incomplete)));
+        assert_eq!(Lexer::new("!404 Not").next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_verbatim_string() {
+        let mut lexer = Lexer::new("=15\r\ntxt:Some string\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::VerbatimString("txt", "Some string"))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut lexer = Lexer::new("~2\r\n+hello\r\n+world\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Set(Some(vec![
+                Token::SimpleString("hello"),
+                Token::SimpleString("world"),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_map() {
+        let mut lexer = Lexer::new("%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Map(Some(vec![
+                Token::SimpleString("first"),
+                Token::Integer(1),
+                Token::SimpleString("second"),
+                Token::Integer(2),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_push() {
+        let mut lexer = Lexer::new(">4\r\n+first\r\n:1\r\n+second\r\n:2\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Push(Some(vec![
+                Token::SimpleString("first"),
+                Token::Integer(1),
+                Token::SimpleString("second"),
+                Token::Integer(2),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut lexer = Lexer::new("+OK\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        assert_eq!(lexer.next(), None);
+
+        lexer.reset(":1000\r\n$5\r\nhello\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(1000)));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(Some("hello"))));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl<'a> Visitor<'a> for Recorder {
+        fn on_simple_string(&mut self, text: &'a str) {
+            self.events.push(format!("simple_string {}", text));
+        }
+
+        fn on_integer(&mut self, value: i64) {
+            self.events.push(format!("integer {}", value));
+        }
+
+        fn on_bulk_string(&mut self, text: Option<&'a str>) {
+            self.events.push(format!("bulk_string {:?}", text));
+        }
+
+        fn on_array_start(&mut self, len: Option<usize>) {
+            self.events.push(format!("array_start {:?}", len));
+        }
+
+        fn on_array_end(&mut self) {
+            self.events.push("array_end".to_string());
+        }
+
+        fn on_streamed_aggregate_start(&mut self, kind: char) {
+            self.events.push(format!("streamed_start {}", kind));
+        }
+
+        fn on_streamed_aggregate_end(&mut self, kind: char, len: usize) {
+            self.events.push(format!("streamed_end {} {}", kind, len));
+        }
+    }
+
+    #[test]
+    fn test_parse_with_callbacks() {
+        let parser = Parser::new("*3\r\n:1\r\n*2\r\n$3\r\nfoo\r\n$-1\r\n:2\r\n+OK\r\n");
+        let mut recorder = Recorder::default();
+        assert_eq!(parser.parse_with_callbacks(&mut recorder), Ok(()));
+        assert_eq!(
+            recorder.events,
+            vec![
+                "array_start Some(3)",
+                "integer 1",
+                "array_start Some(2)",
+                "bulk_string Some(\"foo\")",
+                "bulk_string None",
+                "array_end",
+                "integer 2",
+                "array_end",
+                "simple_string OK",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_with_callbacks_streamed() {
+        let parser = Parser::new("*?\r\n:1\r\n*?\r\n$3\r\nfoo\r\n.\r\n.\r\n*?\r\n.\r\n+OK\r\n");
+        let mut recorder = Recorder::default();
+        assert_eq!(parser.parse_with_callbacks(&mut recorder), Ok(()));
+        assert_eq!(
+            recorder.events,
+            vec![
+                "streamed_start *",
+                "integer 1",
+                "streamed_start *",
+                "bulk_string Some(\"foo\")",
+                "streamed_end * 1",
+                "streamed_end * 2",
+                "streamed_start *",
+                "streamed_end * 0",
+                "simple_string OK",
+            ]
+        );
+
+        let mut recorder = Recorder::default();
+        assert_eq!(
+            Parser::new("%?\r\n+a\r\n:1\r\n.\r\n").parse_with_callbacks(&mut recorder),
+            Ok(())
+        );
+        assert_eq!(
+            recorder.events,
+            vec![
+                "streamed_start %",
+                "simple_string a",
+                "integer 1",
+                "streamed_end % 1"
+            ]
+        );
+
+        // 和长度已知的聚合类型一样, 数据不够时已经通知过的回调不会撤回
+        let mut recorder = Recorder::default();
+        assert_eq!(
+            Parser::new("*?\r\n:1\r\n").parse_with_callbacks(&mut recorder),
+            Err(Error::Incomplete)
+        );
+        assert_eq!(recorder.events, vec!["streamed_start *", "integer 1"]);
+    }
+
+    #[test]
+    fn test_as_str() {
+        assert_eq!(Token::SimpleString("OK").as_str(), Some("OK"));
+        assert_eq!(Token::Error("ERR").as_str(), Some("ERR"));
+        assert_eq!(Token::BulkString(Some("hello")).as_str(), Some("hello"));
+        assert_eq!(Token::BulkString(None).as_str(), None);
+        assert_eq!(Token::BigErr("SYNTAX").as_str(), Some("SYNTAX"));
+        assert_eq!(
+            Token::VerbatimString("txt", "Some string").as_str(),
+            Some("Some string")
+        );
+        assert_eq!(Token::Integer(1).as_str(), None);
+        assert_eq!(Token::Double("3.14").as_str(), None);
+        assert_eq!(Token::Array(Some(vec![])).as_str(), None);
+    }
+
+    #[test]
+    fn test_as_bytes() {
+        assert_eq!(Token::SimpleString("OK").as_bytes(), Some(&b"OK"[..]));
+        assert_eq!(Token::BulkString(Some("")).as_bytes(), Some(&b""[..]));
+        assert_eq!(Token::BulkString(None).as_bytes(), None);
+        assert_eq!(Token::Boolean(true).as_bytes(), None);
+    }
+
+    #[test]
+    fn test_error_code() {
+        let mut lexer = Lexer::new("-ERR unknown command 'FOO'\r\n-WRONGTYPE Operation against a key holding the wrong kind of value\r\n-10086\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.error_code(), Some("ERR"));
+        assert_eq!(token.split_error(), Some(("ERR", "unknown command 'FOO'")));
+
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.error_code(), Some("WRONGTYPE"));
+
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.error_code(), None);
+        assert_eq!(Token::SimpleString("OK").error_code(), None);
+    }
+
+    #[test]
+    fn test_collection_len() {
+        assert_eq!(collection_len(0), Ok(0));
+        assert_eq!(collection_len(3), Ok(3));
+        assert_eq!(collection_len(-1), Err(Error::InvalidLength(-1)));
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_collection_len_32bit() {
+        assert_eq!(
+            collection_len(i64::MAX),
+            Err(Error::CountOverflow(i64::MAX))
+        );
+        let mut lexer = Lexer::new("*9223372036854775807\r\n");
+        assert_eq!(lexer.next().unwrap(), Err(Error::CountOverflow(i64::MAX)));
+    }
+
+    #[test]
+    fn test_streaming_array() {
+        let input = "*3\r\n:1\r\n*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n:3\r\n*-1\r\n\r\n";
+        let options = ParseOptions {
+            streaming: true,
+            ..ParseOptions::default()
+        };
+        let mut lexer = Lexer::with_options(input, options);
+        assert_eq!(lexer.next().unwrap(), Ok(Token::ArrayHeader(3)));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(1)));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::ArrayHeader(2)));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(Some("foo"))));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(Some("bar"))));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(3)));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Array(None)));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new(input);
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![
+                Token::Integer(1),
+                Token::Array(Some(vec![
+                    Token::BulkString(Some("foo")),
+                    Token::BulkString(Some("bar")),
+                ])),
+                Token::Integer(3),
+            ])))
+        );
+    }
+
+    #[test]
+    fn test_streaming_array_nested() {
+        let options = ParserBuilder::new().streaming(true).options();
+        let mut lexer = Lexer::with_options("%1\r\n+a\r\n*2\r\n:1\r\n:2\r\n+OK\r\n", options);
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Map(Some(vec![
+                Token::SimpleString("a"),
+                Token::Array(Some(vec![Token::Integer(1), Token::Integer(2)])),
+            ])))
+        );
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::with_options(">2\r\n+message\r\n*1\r\n:1\r\n", options);
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Push(Some(vec![
+                Token::SimpleString("message"),
+                Token::Array(Some(vec![Token::Integer(1)])),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_integer_without_digits() {
+        let mut lexer = Lexer::new(":abc\r\n");
+        assert_eq!(lexer.next().unwrap(), Err(Error::IntegerEmpty));
+
+        let mut lexer = Lexer::new(":-\r\n");
+        assert_eq!(lexer.next().unwrap(), Err(Error::IntegerEmpty));
+
+        let mut lexer = Lexer::new("*x\r\n");
+        assert_eq!(lexer.next().unwrap(), Err(Error::IntegerEmpty));
+
+        let mut lexer = Lexer::new(":");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_attribute() {
+        let mut lexer = Lexer::new("*2\r\n|1\r\n+k\r\n+v\r\n:1\r\n:2\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![
+                Token::Integer(1),
+                Token::Integer(2)
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new("%1\r\n+key\r\n|1\r\n+ttl\r\n:10\r\n+value\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Map(Some(vec![
+                Token::SimpleString("key"),
+                Token::SimpleString("value"),
+            ])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_attribute_with_callbacks() {
+        let parser = Parser::new("|1\r\n+k\r\n+v\r\n*1\r\n|1\r\n+k\r\n+v\r\n:1\r\n");
+        let mut recorder = Recorder::default();
+        assert_eq!(parser.parse_with_callbacks(&mut recorder), Ok(()));
+        assert_eq!(
+            recorder.events,
+            vec!["array_start Some(1)", "integer 1", "array_end"]
+        );
+    }
+
+    #[test]
+    fn test_eq_native() {
+        let mut lexer = Lexer::new(":5\r\n+OK\r\n#t\r\n$3\r\nfoo\r\n");
+        let integer = lexer.next().unwrap().unwrap();
+        assert!(integer == 5);
+        assert!(integer != 6);
+        assert!(integer != "5");
+        assert!(integer != true);
+
+        let simple_string = lexer.next().unwrap().unwrap();
+        assert!(simple_string == "OK");
+        assert!(simple_string != "ok");
+        assert!(simple_string != 0);
+
+        let boolean = lexer.next().unwrap().unwrap();
+        assert!(boolean == true);
+        assert!(boolean != false);
+        assert!(boolean != 1);
+
+        let bulk_string = lexer.next().unwrap().unwrap();
+        assert!(bulk_string == "foo");
+        let empty = "";
+        assert!(Token::BulkString(None) != empty);
+        assert!(Token::<str>::Double("5") != 5);
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        let parser = Parser::new("+OK\r\n:1\r\n$3\r\nfoo\r\n");
+        assert_eq!(
+            parser.parse_strict(),
+            Ok(vec![
+                Token::SimpleString("OK"),
+                Token::Integer(1),
+                Token::BulkString(Some("foo")),
+            ])
+        );
+
+        let parser = Parser::new("+OK\r\n\r\n  ");
+        assert_eq!(parser.parse_strict(), Ok(vec![Token::SimpleString("OK")]));
+    }
+
+    #[test]
+    fn test_parse_strict_trailing() {
+        let parser = Parser::new("+OK\r\n$5\r\nab");
+        assert_eq!(parser.parse_strict(), Err(Error::Incomplete));
+
+        let parser = Parser::new("+OK\r\nxyz");
+        assert_eq!(parser.parse_strict(), Err(Error::UnknownType('x')));
+    }
+
+    #[test]
+    fn test_skip_line_at_buffer_end() {
+        let mut lexer = Lexer::new("+OK\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new("+OK\n:1\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(1)));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new("$3\r\nfoo\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(Some("foo"))));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new("*-1\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Array(None)));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new("#t\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Boolean(true)));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_skip_line_missing_terminator() {
+        let mut lexer = Lexer::new("+OK\r");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new(":1");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_null() {
+        let mut lexer = Lexer::new("_\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Null));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_normalize_nulls() {
+        let mut lexer = Lexer::new("$-1\r\n*-1\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(None)));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Array(None)));
+
+        let options = ParseOptions {
+            resp3_normalize_nulls: true,
+            ..ParseOptions::default()
+        };
+        let mut lexer = Lexer::with_options("$-1\r\n*-1\r\n*1\r\n$-1\r\n", options);
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Null));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Null));
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![Token::Null])))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_len() {
+        let mut lexer = Lexer::new(
+            "*2\r\n:1\r\n:2\r\n~1\r\n+a\r\n*0\r\n%2\r\n+a\r\n:1\r\n+b\r\n:2\r\n>1\r\n+x\r\n*-1\r\n:1\r\n",
+        );
+        assert_eq!(lexer.next().unwrap().unwrap().len(), Some(2));
+        assert_eq!(lexer.next().unwrap().unwrap().len(), Some(1));
+        let empty = lexer.next().unwrap().unwrap();
+        assert_eq!(empty.len(), Some(0));
+        assert_eq!(empty.is_empty(), Some(true));
+        assert_eq!(lexer.next().unwrap().unwrap().len(), Some(2));
+        assert_eq!(lexer.next().unwrap().unwrap().len(), Some(1));
+        assert_eq!(lexer.next().unwrap().unwrap().len(), None);
+        assert_eq!(lexer.next().unwrap().unwrap().len(), None);
+    }
+
+    #[test]
+    fn test_double_forms() {
+        let mut lexer = Lexer::new(",1e-3\r\n,.5\r\n,5.\r\n,+1.5e-3\r\n,-.5E+2\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("1e-3")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double(".5")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("5.")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("+1.5e-3")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("-.5E+2")));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_double_invalid() {
+        let mut lexer = Lexer::new(",.\r\n");
+        assert!(matches!(lexer.next().unwrap(), Err(Error::F64(_))));
+
+        let mut lexer = Lexer::new(",abc\r\n");
+        assert!(matches!(lexer.next().unwrap(), Err(Error::F64(_))));
+
+        let mut lexer = Lexer::new(",1.5");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_double_exponent_sign() {
+        let mut lexer = Lexer::new(",5.9e-3\r\n,5.9E+3\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("5.9e-3")));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("5.9E+3")));
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new(",5.9e-\r\n");
+        assert!(matches!(lexer.next().unwrap(), Err(Error::F64(_))));
+    }
+
+    #[test]
+    fn test_fused() {
+        let mut lexer = Lexer::new("+OK\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        for _ in 0..3 {
+            assert_eq!(lexer.next(), None);
+        }
+
+        // 中途不完整的frame返回None之后, 剩下的字节也不会再被解析
+        let mut lexer = Lexer::new("#\r\n+OK\r\n");
+        assert_eq!(lexer.next(), None);
+        for _ in 0..3 {
+            assert_eq!(lexer.next(), None);
+        }
+
+        lexer.reset("+OK\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+    }
+
+    #[test]
+    fn test_verbatim_string_length() {
+        // 声明的长度比剩下的数据长
+        let mut lexer = Lexer::new("=1000\r\ntxt:abc\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new("=9223372036854775807\r\ntxt:abc\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new("=-1\r\ntxt:abc\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::InvalidLength(-1))));
+    }
+
+    #[test]
+    fn test_type_name() {
+        let tokens: [(Token, &str); 17] = [
+            (Token::SimpleString("OK"), "simple_string"),
+            (Token::Error("ERR"), "error"),
+            (Token::Integer(1), "integer"),
+            (Token::BulkString(None), "bulk_string"),
+            (Token::Array(Some(vec![])), "array"),
+            (Token::Boolean(true), "boolean"),
+            (Token::Set(None), "set"),
+            (Token::Double("1.5"), "double"),
+            (Token::BigNumber("1"), "big_number"),
+            (Token::BigErr("ERR"), "big_error"),
+            (Token::VerbatimString("txt", "abc"), "verbatim_string"),
+            (Token::Map(None), "map"),
+            (Token::Push(None), "push"),
+            (Token::ArrayHeader(2), "array_header"),
+            (Token::Null, "null"),
+            (Token::StreamedString(vec!["a"]), "streamed_string"),
+            (Token::Attribute(vec![]), "attribute"),
+        ];
+        for (token, name) in tokens {
+            assert_eq!(token.type_name(), name);
+        }
+    }
+
+    #[test]
+    fn test_verbatim_format() {
+        let mut lexer = Lexer::new("=15\r\ntxt:Some string\r\n=7\r\nmkd:# a\r\n=7\r\nfoo:abc\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.verbatim_format(), Some(VerbatimFormat::Text));
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.verbatim_format(), Some(VerbatimFormat::Markdown));
+        assert_eq!(token.as_str(), Some("# a"));
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.verbatim_format(), Some(VerbatimFormat::Other("foo")));
+
+        assert_eq!(Token::SimpleString("txt").verbatim_format(), None);
+    }
+
+    #[test]
+    fn test_verbatim_format_malformed() {
+        let mut lexer = Lexer::new("=2\r\nt:\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::MalformedVerbatim)));
+
+        let mut lexer = Lexer::new("=7\r\nt\r\n:abc\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::MalformedVerbatim)));
+
+        let mut lexer = Lexer::new("=8\r\nté:abc\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::MalformedVerbatim)));
+    }
+
+    #[test]
+    fn test_verbatim_string_utf8() {
+        // 声明的长度在é中间切开
+        let mut lexer = Lexer::new("=5\r\ntxt:é\r\n");
+        assert!(matches!(lexer.next(), Some(Err(Error::Utf8(_)))));
+
+        let mut lexer = Lexer::new("=6\r\ntxt:é\r\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::VerbatimString("txt", "é"))));
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_verbatim_string_terminator() {
+        // 和bulk string一样, payload后面必须是\r\n
+        let mut lexer = Lexer::new("=7\r\ntxt:abc\n");
+        assert_eq!(lexer.next(), Some(Err(Error::MissingTerminator)));
+
+        let mut lexer = Lexer::new("=7\r\ntxt:abcde\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::MissingTerminator)));
+
+        let mut lexer = Lexer::new("=7\r\ntxt:abc\r");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        // payload里可以有\r\n
+        let mut lexer = Lexer::new(&b"=9\r\ntxt:a\r\nbc\r\n"[..]);
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::VerbatimString(&b"txt"[..], &b"a\r\nbc"[..])))
+        );
+    }
+
+    #[test]
+    fn test_collection_truncated() {
+        let mut lexer = Lexer::new("*3\r\n:1\r\n:2\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new("%1\r\n+key\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new("*3\r\n:1\r\n:2\r\n");
+        assert_eq!(
+            lexer.visit(&mut Recorder::default()),
+            Some(Err(Error::Incomplete))
+        );
+
+        let mut lexer = Lexer::new("");
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut lexer = Lexer::new("+OK\r\n:1\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+
+        // 克隆出来的lexer和原来的各自前进
+        let mut speculative = lexer.clone();
+        assert_eq!(speculative.next().unwrap(), Ok(Token::Integer(1)));
+        assert_eq!(speculative.next(), None);
+        assert_eq!(lexer.position(), 5);
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Integer(1)));
+
+        let mut lexer = Lexer::new(b"*2\r\n$1\r\na\r\n:2\r\n".as_slice());
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.clone(), token);
+    }
+
+    #[test]
+    fn test_peek_type() {
+        let buf = ">2\r\n+message\r\n+hello\r\n+OK\r\n";
+        assert_eq!(Parser::new(buf).peek_type(), Some('>'));
+
+        let mut lexer = Lexer::new(buf);
+        assert_eq!(lexer.peek_type(), Some('>'));
+        assert_eq!(lexer.peek_type(), Some('>'));
+        assert!(matches!(lexer.next().unwrap(), Ok(Token::Push(_))));
+        assert_eq!(lexer.peek_type(), Some('+'));
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+        assert_eq!(lexer.peek_type(), None);
+
+        // parser的选项同样生效
+        let buf = "\r\n >1\r\n+message\r\n";
+        assert_eq!(Parser::new(buf).peek_type(), Some('\r'));
+        let parser = Parser::builder()
+            .skip_whitespace_between_frames(true)
+            .build(buf);
+        assert_eq!(parser.peek_type(), Some('>'));
+        assert!(matches!(parser.parse_one(), Ok(Token::Push(_))));
+        let parser = Parser::builder()
+            .skip_whitespace_between_frames(true)
+            .build(" \r\n");
+        assert_eq!(parser.peek_type(), None);
+    }
+
+    #[test]
+    fn test_negative_set_map() {
+        let mut lexer = Lexer::new("~-1\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::InvalidLength(-1))));
+
+        let mut lexer = Lexer::new("%-1\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::InvalidLength(-1))));
+
+        let mut lexer = Lexer::new("~-1\r\n");
+        assert_eq!(
+            lexer.visit(&mut Recorder::default()),
+            Some(Err(Error::InvalidLength(-1)))
+        );
+
+        // null数组不受影响
+        let mut lexer = Lexer::new("*-1\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::Array(None)));
+    }
+
+    #[test]
+    fn test_bulk_string_fast_path() {
+        // 切片和逐字节扫描的结果一致
+        for payload in ["", "a", "hello world", "你好"] {
+            let buf = alloc::format!("${}\r\n{}\r\n", payload.len(), payload);
+            let mut lexer = Lexer::new(buf.as_str());
+            assert_eq!(lexer.next().unwrap(), Ok(Token::BulkString(Some(payload))));
+            assert_eq!(lexer.position(), buf.len());
+
+            let mut lexer = Lexer::new(buf.as_bytes());
+            assert_eq!(
+                lexer.next().unwrap(),
+                Ok(Token::BulkString(Some(payload.as_bytes())))
+            );
+            assert_eq!(lexer.position(), buf.len());
+        }
+
+        // 字节lexer按长度读取, payload里可以有\r\n
+        let mut lexer = Lexer::new(b"$4\r\na\r\nb\r\n".as_slice());
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::BulkString(Some(b"a\r\nb".as_slice())))
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let parser = Parser::new("+OK\r\n:1\r\n$3\r\nfoo\r\n");
+        let tokens: Result<Vec<_>, _> = parser.frames().collect();
+        assert_eq!(
+            tokens,
+            Ok(vec![
+                Token::SimpleString("OK"),
+                Token::Integer(1),
+                Token::BulkString(Some("foo")),
+            ])
+        );
+
+        let parser = Parser::new("+OK\r\n~-1\r\n");
+        let mut frames = parser.frames();
+        assert_eq!(frames.next(), Some(Ok(Token::SimpleString("OK"))));
+        assert_eq!(frames.next(), Some(Err(Error::InvalidLength(-1))));
+    }
+
+    #[test]
+    fn test_strict_utf8() {
+        let buf = b"+caf\xe9\r\n-ERR \xff\r\n+OK\r\n".as_slice();
+
+        let mut lexer = Lexer::new(buf);
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token, Token::SimpleString(b"caf\xe9".as_slice()));
+        assert!(matches!(token.try_as_str(), Some(Err(_))));
+        let token = lexer.next().unwrap().unwrap();
+        assert!(matches!(token.try_as_str(), Some(Err(_))));
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.try_as_str(), Some(Ok("OK")));
+        assert_eq!(Token::<[u8]>::Integer(1).try_as_str(), None);
+
+        let options = ParseOptions {
+            strict_utf8: true,
+            ..ParseOptions::default()
+        };
+        let mut lexer = Lexer::with_options(buf, options);
+        assert!(matches!(lexer.next(), Some(Err(Error::Utf8(_)))));
+        assert!(matches!(lexer.next(), Some(Err(Error::Utf8(_)))));
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::SimpleString(b"OK".as_slice())))
+        );
+    }
+
+    #[test]
+    fn test_try_from_token() {
+        assert_eq!(i64::try_from(Token::<str>::Integer(5)), Ok(5));
+        assert_eq!(
+            i64::try_from(Token::SimpleString("5")),
+            Err(Error::TypeMismatch {
+                expected: "integer",
+                found: "simple_string",
+            })
+        );
+
+        assert_eq!(bool::try_from(Token::<str>::Boolean(true)), Ok(true));
+        assert_eq!(
+            bool::try_from(Token::<str>::Integer(1)),
+            Err(Error::TypeMismatch {
+                expected: "boolean",
+                found: "integer",
+            })
+        );
+
+        let text: Result<&str, _> = Token::BulkString(Some("foo")).try_into();
+        assert_eq!(text, Ok("foo"));
+        assert_eq!(<&str>::try_from(Token::SimpleString("OK")), Ok("OK"));
+        assert_eq!(
+            <&str>::try_from(Token::BulkString(None)),
+            Err(Error::TypeMismatch {
+                expected: "string",
+                found: "bulk_string",
+            })
+        );
+        assert_eq!(
+            <&str>::try_from(Token::Null),
+            Err(Error::TypeMismatch {
+                expected: "string",
+                found: "null",
+            })
+        );
+    }
+
+    #[test]
+    fn test_bulk_string_short() {
+        let mut lexer = Lexer::new("$5\r\nhi\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new(b"$5\r\nhi\r\n".as_slice());
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        // 补齐之后按长度读取
+        let mut lexer = Lexer::new(b"$5\r\nhi\r\nx\r\n".as_slice());
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::BulkString(Some(b"hi\r\nx".as_slice())))
+        );
+
+        // 长度落在多字节字符中间
+        let mut lexer = Lexer::new("$1\r\n你\r\n");
+        assert!(matches!(lexer.next(), Some(Err(Error::Utf8(_)))));
+    }
+
+    #[test]
+    fn test_stats() {
+        let buf = "+OK\r\n:1\r\n*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+        let parser = Parser::new(buf);
+        let mut frames = parser.frames();
+        assert_eq!(frames.stats(), ParseStats::default());
+        frames.next().unwrap().unwrap();
+        assert_eq!(
+            frames.stats(),
+            ParseStats {
+                bytes: 5,
+                frames: 1,
+            }
+        );
+        assert_eq!(frames.by_ref().count(), 2);
+        assert_eq!(
+            frames.stats(),
+            ParseStats {
+                bytes: buf.len(),
+                frames: 3,
+            }
+        );
+
+        // 每次调用frames都重新开始统计
+        assert_eq!(parser.frames().stats(), ParseStats::default());
+
+        // Parser可以在线程之间共享
+        fn assert_sync<T: Sync>(_: &T) {}
+        assert_sync(&parser);
+
+        // 出错的frame只统计字节
+        let parser = Parser::new("+OK\r\n~-1\r\n");
+        let mut frames = parser.frames();
+        assert_eq!(frames.by_ref().count(), 2);
+        assert_eq!(
+            frames.stats(),
+            ParseStats {
+                bytes: 10,
+                frames: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_streamed_aggregate() {
+        let mut lexer = Lexer::new("*?\r\n:1\r\n+two\r\n*?\r\n.\r\n.\r\n~?\r\n:1\r\n.\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Array(Some(vec![
+                Token::Integer(1),
+                Token::SimpleString("two"),
+                Token::Array(Some(vec![])),
+            ])))
+        );
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Set(Some(vec![Token::Integer(1)])))
+        );
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new("%?\r\n+a\r\n:1\r\n.\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::Map(Some(vec![
+                Token::SimpleString("a"),
+                Token::Integer(1)
+            ])))
+        );
+
+        // map不能在key之后结束
+        let mut lexer = Lexer::new("%?\r\n+a\r\n.\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::UnknownType('.'))));
+
+        let mut lexer = Lexer::new("*?\r\n:1\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_streamed_string() {
+        let mut lexer = Lexer::new("$?\r\n;4\r\nHell\r\n;5\r\no wor\r\n;1\r\nd\r\n;0\r\n+OK\r\n");
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::StreamedString(vec!["Hell", "o wor", "d"]))
+        );
+        assert_eq!(lexer.next().unwrap(), Ok(Token::SimpleString("OK")));
+
+        let mut lexer = Lexer::new("$?\r\n;0\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::StreamedString(vec![])));
+
+        let mut lexer = Lexer::new("$?\r\n;4\r\nHell\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new("$?\r\n:4\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::UnknownType(':'))));
+    }
+
+    #[test]
+    fn test_get() {
+        let mut lexer = Lexer::new("*3\r\n:1\r\n*1\r\n+nested\r\n$3\r\nfoo\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.get(0), Some(&Token::Integer(1)));
+        assert_eq!(
+            token.get(1).and_then(|token| token.get(0)),
+            Some(&Token::SimpleString("nested"))
+        );
+        assert_eq!(token.get(3), None);
+        assert_eq!(Token::<str>::Integer(1).get(0), None);
+    }
+
+    #[test]
+    fn test_get_by_key() {
+        let mut lexer = Lexer::new(
+            b"%3\r\n+server\r\n$5\r\nredis\r\n$5\r\nproto\r\n:3\r\n:1\r\n:2\r\n".as_slice(),
+        );
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(
+            token.get_by_key("server"),
+            Some(&Token::BulkString(Some(b"redis".as_slice())))
+        );
+        assert_eq!(token.get_by_key("proto"), Some(&Token::Integer(3)));
+        // 非字符串的key不参与匹配
+        assert_eq!(token.get_by_key("1"), None);
+        assert_eq!(token.get_by_key("missing"), None);
+        assert_eq!(Token::SimpleString("server").get_by_key("server"), None);
+    }
+
+    #[test]
+    fn test_bulk_string_terminator() {
+        let mut lexer = Lexer::new("$2\r\nhiXX\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::MissingTerminator)));
+
+        let mut lexer = Lexer::new("$2\r\nhi\n");
+        assert_eq!(lexer.next(), Some(Err(Error::MissingTerminator)));
+
+        let mut lexer = Lexer::new("$2\r\nhi\r");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new("$2\r\nhi");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(ParserBuilder::new().options(), ParseOptions::default());
+
+        let buf = "*1\r\n*1\r\n:1\r\n";
+        let parser = Parser::builder().max_depth(2).build(buf);
+        assert_eq!(
+            parser.parse_strict(),
+            Ok(vec![Token::Array(Some(vec![Token::Array(Some(vec![
+                Token::Integer(1)
+            ]))]))])
+        );
+
+        let parser = Parser::builder().max_depth(1).build(buf);
+        assert_eq!(parser.parse_strict(), Err(Error::DepthExceeded(1)));
+        assert_eq!(
+            parser.parse_with_callbacks(&mut Recorder::default()),
+            Err(Error::DepthExceeded(1))
+        );
+
+        let parser = Parser::builder()
+            .max_depth(1)
+            .resp3_normalize_nulls(true)
+            .build("*-1\r\n*1\r\n:1\r\n");
+        assert_eq!(
+            parser.parse_strict(),
+            Ok(vec![
+                Token::Null,
+                Token::Array(Some(vec![Token::Integer(1)]))
+            ])
+        );
+    }
+
+    #[test]
+    fn test_into_iterator() {
+        let parser = Parser::new("+OK\r\n:1\r\n$3\r\nfoo\r\n");
+        let mut tokens = Vec::new();
+        for frame in &parser {
+            tokens.push(frame.unwrap());
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                Token::SimpleString("OK"),
+                Token::Integer(1),
+                Token::BulkString(Some("foo")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redis_error() {
+        let mut lexer = Lexer::new(
+            "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n!21\r\nSYNTAX invalid syntax\r\n-oops\r\n",
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap().as_redis_error(),
+            Some(RedisError {
+                code: "WRONGTYPE",
+                message: "Operation against a key holding the wrong kind of value",
+            })
+        );
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token, Token::BigErr("SYNTAX invalid syntax"));
+        assert_eq!(
+            token.as_redis_error(),
+            Some(RedisError {
+                code: "SYNTAX",
+                message: "invalid syntax",
+            })
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap().as_redis_error(),
+            Some(RedisError {
+                code: "",
+                message: "oops",
+            })
+        );
+        assert_eq!(Token::SimpleString("ERR").as_redis_error(), None);
+    }
+
+    #[test]
+    fn test_verbatim_missing_colon() {
+        let mut lexer = Lexer::new("=10\r\ntxtSomeXXX\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::MalformedVerbatim)));
+
+        let mut lexer = Lexer::new(b"=4\r\ntxt;\r\n".as_slice());
+        assert_eq!(lexer.next(), Some(Err(Error::MalformedVerbatim)));
+
+        let mut lexer = Lexer::new("=4\r\ntxt:\r\n");
+        assert_eq!(lexer.next().unwrap(), Ok(Token::VerbatimString("txt", "")));
+    }
+
+    #[test]
+    fn test_predicates() {
+        let mut lexer = Lexer::new("*2\r\n:1\r\n-ERR\r\n%1\r\n+a\r\n,1.5\r\n");
+        let array = lexer.next().unwrap().unwrap();
+        assert!(array.is_array());
+        assert!(!array.is_map());
+        assert!(array.get(0).unwrap().is_integer());
+        assert!(!array.get(0).unwrap().is_error());
+        assert!(array.get(1).unwrap().is_error());
+        assert!(!array.get(1).unwrap().is_big_error());
+        let map = lexer.next().unwrap().unwrap();
+        assert!(map.is_map());
+        assert!(!map.is_array());
+        assert!(map.get_by_key("a").unwrap().is_double());
+        assert!(!map.get_by_key("a").unwrap().is_integer());
+
+        type Check = fn(&Token) -> bool;
+        let checks: [(Token, Check); 17] = [
+            (Token::SimpleString("OK"), |token| token.is_simple_string()),
+            (Token::Error("ERR"), |token| token.is_error()),
+            (Token::Integer(1), |token| token.is_integer()),
+            (Token::BulkString(None), |token| token.is_bulk_string()),
+            (Token::Array(None), |token| token.is_array()),
+            (Token::Boolean(true), |token| token.is_boolean()),
+            (Token::Set(None), |token| token.is_set()),
+            (Token::Double("1.5"), |token| token.is_double()),
+            (Token::BigNumber("1"), |token| token.is_big_number()),
+            (Token::BigErr("ERR"), |token| token.is_big_error()),
+            (Token::VerbatimString("txt", "a"), |token| {
+                token.is_verbatim_string()
+            }),
+            (Token::Map(None), |token| token.is_map()),
+            (Token::Push(None), |token| token.is_push()),
+            (Token::ArrayHeader(1), |token| token.is_array_header()),
+            (Token::Null, |token| token.is_null()),
+            (Token::StreamedString(vec![]), |token| {
+                token.is_streamed_string()
+            }),
+            (Token::Attribute(vec![]), |token| token.is_attribute()),
+        ];
+        for (i, (token, check)) in checks.iter().enumerate() {
+            assert!(check(token), "{}", token.type_name());
+            // 换成下一个token时不再匹配
+            let (other, _) = &checks[(i + 1) % checks.len()];
+            assert!(!check(other), "{}", other.type_name());
+        }
+    }
+
+    #[test]
+    fn test_double_canonical() {
+        let mut lexer = Lexer::new(",5.9e3\r\n,1.50\r\n,-0.25\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token, Token::Double("5.9e3"));
+        assert_eq!(token.as_f64(), Some(5900.0));
+        assert_eq!(token.to_resp3_string().as_deref(), Some("5900"));
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.to_resp3_string().as_deref(), Some("1.5"));
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.to_resp3_string().as_deref(), Some("-0.25"));
+
+        let text = |text| Token::<str>::Double(text).to_resp3_string();
+        assert_eq!(text("inf").as_deref(), Some("inf"));
+        assert_eq!(text("-inf").as_deref(), Some("-inf"));
+        assert_eq!(text("nan").as_deref(), Some("nan"));
+        assert_eq!(Token::<str>::Integer(1).as_f64(), None);
+    }
+
+    #[test]
+    fn test_max_bulk_len() {
+        let parser = Parser::builder()
+            .max_bulk_len(1024)
+            .build("$536870912\r\nabc");
+        assert_eq!(parser.parse_strict(), Err(Error::BulkTooLarge(536870912)));
+
+        let parser = Parser::builder()
+            .max_bulk_len(3)
+            .build("$3\r\nfoo\r\n=7\r\ntxt:abc\r\n");
+        let mut frames = parser.frames();
+        assert_eq!(frames.next(), Some(Ok(Token::BulkString(Some("foo")))));
+        assert_eq!(frames.next(), Some(Err(Error::BulkTooLarge(7))));
+
+        // 默认不限制
+        let mut lexer = Lexer::new("$536870912\r\nabc");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_skip_whitespace_between_frames() {
+        let buf = "  +OK\r\n\t\r\n*2\r\n:1\r\n:2\r\n \n";
+        let mut lexer = Lexer::new(buf);
+        assert_eq!(lexer.next(), Some(Err(Error::UnknownType(' '))));
+
+        let parser = Parser::builder()
+            .skip_whitespace_between_frames(true)
+            .build(buf);
+        assert_eq!(
+            parser.frames().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                Token::SimpleString("OK"),
+                Token::Array(Some(vec![Token::Integer(1), Token::Integer(2)])),
+            ])
+        );
+        let mut recorder = Recorder::default();
+        assert_eq!(parser.parse_with_callbacks(&mut recorder), Ok(()));
+
+        // 聚合类型内部仍然严格
+        let parser = Parser::builder()
+            .skip_whitespace_between_frames(true)
+            .build("*1\r\n :1\r\n");
+        assert_eq!(parser.parse_strict(), Err(Error::UnknownType(' ')));
+    }
+
+    #[test]
+    fn test_walk() {
+        let mut lexer =
+            Lexer::new("*3\r\n:1\r\n%1\r\n+key\r\n~2\r\n>1\r\n-ERR deep\r\n#t\r\n$-1\r\n");
+        let token = lexer.next().unwrap().unwrap();
+
+        let mut count = 0;
+        token.walk(|_| count += 1);
+        assert_eq!(count, 9);
+
+        let mut found = None;
+        token.walk(|token| {
+            if let Token::Error(text) = token {
+                found = Some(*text);
+            }
+        });
+        assert_eq!(found, Some("ERR deep"));
+
+        let mut names = Vec::new();
+        token.walk(|token| names.push(token.type_name()));
+        assert_eq!(
+            names,
+            [
+                "array",
+                "integer",
+                "map",
+                "simple_string",
+                "set",
+                "push",
+                "error",
+                "boolean",
+                "bulk_string",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_boolean_invalid() {
+        let mut lexer = Lexer::new("#x\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Boolean('x'))));
+
+        let mut lexer = Lexer::new("#true\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Boolean('r'))));
+
+        let mut lexer = Lexer::new("#t \r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Boolean(' '))));
+    }
+
+    #[test]
+    fn test_parse_into() {
+        let mut tokens = Vec::new();
+        assert_eq!(Parser::new("+OK\r\n:1\r\n").parse_into(&mut tokens), Ok(2));
+        assert_eq!(tokens, vec![Token::SimpleString("OK"), Token::Integer(1)]);
+        let capacity = tokens.capacity();
+
+        // 第二次解析前会清空之前的结果
+        assert_eq!(Parser::new("$3\r\nfoo\r\n").parse_into(&mut tokens), Ok(1));
+        assert_eq!(tokens, vec![Token::BulkString(Some("foo"))]);
+        assert_eq!(tokens.capacity(), capacity);
+
+        assert_eq!(
+            Parser::new("+OK\r\n~-1\r\n").parse_into(&mut tokens),
+            Err(Error::InvalidLength(-1))
+        );
+        assert_eq!(Parser::new("").parse_into(&mut tokens), Ok(0));
+        assert!(tokens.is_empty());
+    }
+
+    #[test]
+    fn test_eof_marked_string() {
+        let mark = "0123456789abcdef0123456789abcdef01234567";
+        let buf = [
+            b"$EOF:".as_slice(),
+            mark.as_bytes(),
+            b"\r\nREDIS0011\r\n\xfa",
+            mark.as_bytes(),
+            b"+OK\r\n",
+        ]
+        .concat();
+        let mut lexer = Lexer::new(buf.as_slice());
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::BulkString(Some(b"REDIS0011\r\n\xfa".as_slice())))
+        );
+        assert_eq!(
+            lexer.next().unwrap(),
+            Ok(Token::SimpleString(b"OK".as_slice()))
+        );
+
+        // 还没读到结尾的分隔符
+        let buf = alloc::format!("$EOF:{mark}\r\nREDIS0011{}", &mark[..20]);
+        let mut lexer = Lexer::new(buf.as_str());
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new("$EOF:0123\r\nREDIS0123");
+        assert_eq!(lexer.next(), Some(Err(Error::MalformedEofMark)));
+
+        for prefix in ["$E", "$EO", "$EOF", "$EOF:"] {
+            let mut lexer = Lexer::new(prefix);
+            assert_eq!(lexer.next(), Some(Err(Error::Incomplete)), "{:?}", prefix);
+        }
+
+        // 分隔符后面的\r之后不是\n
+        let buf = alloc::format!("$EOF:{mark}\r");
+        assert_eq!(
+            Lexer::new(buf.as_str()).next(),
+            Some(Err(Error::Incomplete))
+        );
+        let buf = alloc::format!("$EOF:{mark}\rxREDIS0011{mark}");
+        assert_eq!(
+            Lexer::new(buf.as_str()).next(),
+            Some(Err(Error::MissingTerminator))
+        );
+
+        // 超过max_bulk_len之后不用等到分隔符出现
+        let options = ParserBuilder::new().max_bulk_len(9).options();
+        let buf = alloc::format!("$EOF:{mark}\r\nREDIS0011{mark}");
+        assert_eq!(
+            Lexer::with_options(buf.as_str(), options).next(),
+            Some(Ok(Token::BulkString(Some("REDIS0011"))))
+        );
+        let buf = alloc::format!("$EOF:{mark}\r\nREDIS0011x{}", &mark[..39]);
+        assert_eq!(
+            Lexer::with_options(buf.as_str(), options).next(),
+            Some(Err(Error::BulkTooLarge(10)))
+        );
+        let buf = alloc::format!("$EOF:{mark}\r\nREDIS0011x{}", &mark[..38]);
+        assert_eq!(
+            Lexer::with_options(buf.as_str(), options).next(),
+            Some(Err(Error::Incomplete))
+        );
+    }
+
+    #[test]
+    fn test_negative_length() {
+        let mut lexer = Lexer::new("*-1\r\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::Array(None))));
+
+        let mut lexer = Lexer::new("*-5\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::InvalidLength(-5))));
+
+        let mut lexer = Lexer::new("$-2\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::InvalidLength(-2))));
+
+        let mut lexer = Lexer::new(">-3\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::InvalidLength(-3))));
+
+        let mut recorder = Recorder::default();
+        let mut lexer = Lexer::new("*-5\r\n");
+        assert_eq!(
+            lexer.visit(&mut recorder),
+            Some(Err(Error::InvalidLength(-5)))
+        );
+    }
+
+    #[test]
+    fn test_as_pairs() {
+        let mut lexer = Lexer::new("%2\r\n+a\r\n:1\r\n+b\r\n:2\r\n");
+        let map = lexer.next().unwrap().unwrap();
+        let mut lexer = Lexer::new("*4\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n:2\r\n");
+        let array = lexer.next().unwrap().unwrap();
+
+        let pairs = map.as_pairs().unwrap();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!(pairs[0], (&Token::SimpleString("a"), &Token::Integer(1)));
+        assert_eq!(pairs[1], (&Token::SimpleString("b"), &Token::Integer(2)));
+
+        let pairs = array.as_pairs().unwrap();
+        assert_eq!(
+            pairs[0],
+            (&Token::BulkString(Some("a")), &Token::Integer(1))
+        );
+        assert_eq!(
+            pairs[1],
+            (&Token::BulkString(Some("b")), &Token::Integer(2))
+        );
+
+        let mut lexer = Lexer::new("*3\r\n:1\r\n:2\r\n:3\r\n");
+        assert_eq!(lexer.next().unwrap().unwrap().as_pairs(), None);
+        assert_eq!(Token::<str>::Integer(1).as_pairs(), None);
+    }
+
+    #[test]
+    fn test_next_with_len() {
+        let mut lexer = Lexer::new("$5\r\nhello\r\n:1\r\n");
+        assert_eq!(
+            lexer.next_with_len(),
+            Some((Ok(Token::BulkString(Some("hello"))), 11))
+        );
+        assert_eq!(lexer.next_with_len(), Some((Ok(Token::Integer(1)), 4)));
+        assert_eq!(lexer.next_with_len(), None);
+    }
+
+    #[test]
+    fn test_big_number_empty() {
+        let mut lexer = Lexer::new("(\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::BigNumberEmpty)));
+
+        let mut lexer = Lexer::new("(-\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::BigNumberEmpty)));
+
+        // 符号后面数据就没了, 当作不完整
+        let mut lexer = Lexer::new("(-");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_protocol() {
+        let mut lexer = Lexer::new("#t\r\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::Boolean(true))));
+
+        let options = ParserBuilder::new().protocol(Protocol::Resp2).options();
+        let mut lexer = Lexer::with_options("#t\r\n", options);
+        assert_eq!(lexer.next(), Some(Err(Error::Resp3NotAllowed('#'))));
+
+        // 嵌套在RESP2数组里的RESP3类型也会报错
+        let mut lexer = Lexer::with_options("*2\r\n:1\r\n_\r\n", options);
+        assert_eq!(lexer.next(), Some(Err(Error::Resp3NotAllowed('_'))));
+
+        let mut lexer = Lexer::with_options("*2\r\n:1\r\n$3\r\nfoo\r\n", options);
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::Array(Some(vec![
+                Token::Integer(1),
+                Token::BulkString(Some("foo")),
+            ]))))
+        );
+
+        let mut recorder = Recorder::default();
+        let mut lexer = Lexer::with_options("%1\r\n+a\r\n:1\r\n", options);
+        assert_eq!(
+            lexer.visit(&mut recorder),
+            Some(Err(Error::Resp3NotAllowed('%')))
+        );
+    }
+
+    #[test]
+    fn test_spanned() {
+        let mut lexer = Lexer::new("+OK\r\n$5\r\nhello\r\n");
+        let mut spanned = lexer.spanned();
+        assert_eq!(spanned.next(), Some((Ok(Token::SimpleString("OK")), 0..5)));
+        assert_eq!(
+            spanned.next(),
+            Some((Ok(Token::BulkString(Some("hello"))), 5..16))
+        );
+        assert_eq!(spanned.next(), None);
+
+        let options = ParserBuilder::new()
+            .skip_whitespace_between_frames(true)
+            .options();
+        let mut lexer = Lexer::with_options(":1\r\n\r\n:2\r\n", options);
+        let spans: Vec<_> = lexer.spanned().map(|(_, span)| span).collect();
+        assert_eq!(spans, vec![0..4, 6..10]);
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let data = ":99999999999999999999999\r\n:-99999999999999999999999\r\n:1\r\n";
+        let mut lexer = Lexer::new(data);
+        assert!(matches!(lexer.next(), Some(Err(Error::I64(_)))));
+
+        let options = ParserBuilder::new()
+            .integer_overflow_as_big_number(true)
+            .options();
+        let mut lexer = Lexer::with_options(data, options);
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::BigNumber("99999999999999999999999")))
+        );
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::BigNumber("-99999999999999999999999")))
+        );
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(1))));
+
+        // 只有溢出才会转换, 其它错误照常返回
+        let mut lexer = Lexer::with_options(":\r\n", options);
+        assert_eq!(lexer.next(), Some(Err(Error::IntegerEmpty)));
+    }
+
+    #[test]
+    fn test_flatten() {
+        let mut lexer = Lexer::new("*1\r\n*1\r\n:5\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.flatten(), Token::Integer(5));
+
+        let mut lexer = Lexer::new("*1\r\n*2\r\n:1\r\n:2\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(
+            token.flatten(),
+            Token::Array(Some(vec![Token::Integer(1), Token::Integer(2)]))
+        );
+
+        assert_eq!(Token::<str>::Array(None).flatten(), Token::Array(None));
+        assert_eq!(Token::<str>::Null.flatten(), Token::Null);
+    }
+
+    #[test]
+    fn test_next_raw() {
+        let mut lexer = Lexer::new("$3\r\nfoo\r\n*1\r\n:1\r\n");
+        assert_eq!(
+            lexer.next_raw(),
+            Some((Ok(Token::BulkString(Some("foo"))), "$3\r\nfoo\r\n"))
+        );
+        assert_eq!(
+            lexer.next_raw(),
+            Some((
+                Ok(Token::Array(Some(vec![Token::Integer(1)]))),
+                "*1\r\n:1\r\n"
+            ))
+        );
+        assert_eq!(lexer.next_raw(), None);
+
+        let mut lexer = Lexer::new(&b"$2\r\n\xff\x00\r\n"[..]);
+        let (_, raw) = lexer.next_raw().unwrap();
+        assert_eq!(raw, b"$2\r\n\xff\x00\r\n");
+    }
+
+    #[test]
+    fn test_eq_ignore_ascii_case() {
+        let mut lexer = Lexer::new("+ok\r\n+OKAY\r\n$4\r\npong\r\n:1\r\n");
+        assert!(lexer.next().unwrap().unwrap().eq_ignore_ascii_case("OK"));
+        assert!(!lexer.next().unwrap().unwrap().eq_ignore_ascii_case("OK"));
+        assert!(lexer.next().unwrap().unwrap().eq_ignore_ascii_case("PONG"));
+        assert!(!lexer.next().unwrap().unwrap().eq_ignore_ascii_case("1"));
+
+        let mut lexer = Lexer::new(&b"+Ok\r\n"[..]);
+        assert!(lexer.next().unwrap().unwrap().eq_ignore_ascii_case("OK"));
+    }
+
+    #[test]
+    fn test_scalar_incomplete() {
+        for data in [
+            "+OK",
+            "+",
+            "-ERR",
+            ":12",
+            ":-",
+            "#t",
+            "#",
+            ",1.5",
+            "_",
+            "(123",
+            "!ERR",
+            "!",
+            "!3",
+            "!3\r\nab",
+            "=7\r\ntxt:abc",
+            "*2",
+            "$?",
+            "*?",
+        ] {
+            let mut lexer = Lexer::new(data);
+            assert_eq!(lexer.next(), Some(Err(Error::Incomplete)), "{:?}", data);
+        }
+
+        // 空buffer和完整的frame之后仍然返回None
+        let mut lexer = Lexer::new("");
+        assert_eq!(lexer.next(), None);
+        let mut lexer = Lexer::new("+OK\r\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString("OK"))));
         assert_eq!(lexer.next(), None);
     }
 
     #[test]
-    fn test_array() {
-        let mut lexer = Lexer::new("*0\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Array(Some(vec![]))));
-        assert_eq!(lexer.next(), None);
+    fn test_malformed_terminator() {
+        for data in [
+            "+OK\rX\r\n",
+            "-ERR\rx\r\n",
+            ":12x\r\n+OK\r\n",
+            ",1.5x\r\n",
+            "_x\r\n",
+            "(123x\r\n",
+            "!3\r\nabcx\r\n",
+            "=5\r\ntxt:abcdef\r\n",
+            "$3\r\nfoox\r\n",
+            "*2x\r\n",
+            "~1x\r\n",
+            "%1x\r\n",
+            ">1x\r\n",
+            "|1x\r\n",
+        ] {
+            // 换行符的位置上已经有数据了, 不能当作数据不完整
+            let mut lexer = Lexer::new(data);
+            assert_eq!(
+                lexer.next(),
+                Some(Err(Error::MissingTerminator)),
+                "{:?}",
+                data
+            );
+            let parser = Parser::new(data);
+            assert_eq!(
+                parser.parse_strict(),
+                Err(Error::MissingTerminator),
+                "{:?}",
+                data
+            );
+            assert_eq!(
+                parser.validate(),
+                Err(Error::MissingTerminator),
+                "{:?}",
+                data
+            );
+        }
     }
 
     #[test]
-    fn test_array_2() {
-        let mut lexer = Lexer::new("*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n");
+    fn test_count_frames() {
+        let parser = Parser::new("+OK\r\n*2\r\n:1\r\n:2\r\n:9\r\n");
+        assert_eq!(parser.count_frames(), Ok(3));
+        assert_eq!(Parser::new("").count_frames(), Ok(0));
         assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::Array(Some(vec![
-                Token::BulkString(Some("foo")),
-                Token::BulkString(Some("bar")),
-            ])))
+            Parser::new("+OK\r\n*2\r\n:1\r\n").count_frames(),
+            Err(Error::Incomplete)
+        );
+        assert_eq!(
+            Parser::new("+OK\r\n#x\r\n").count_frames(),
+            Err(Error::Boolean('x'))
+        );
+
+        let parser = Parser::new("*?\r\n:1\r\n.\r\n%?\r\n+a\r\n~?\r\n.\r\n.\r\n>?\r\n.\r\n");
+        assert_eq!(parser.count_frames(), Ok(3));
+        assert_eq!(
+            Parser::new("*?\r\n:1\r\n").count_frames(),
+            Err(Error::Incomplete)
         );
-        assert_eq!(lexer.next(), None);
     }
 
     #[test]
-    fn test_array_3() {
-        let mut lexer = Lexer::new("*3\r\n:1\r\n:2\r\n:3\r\n");
+    fn test_as_i128() {
+        let mut lexer = Lexer::new("(170141183460469231731687303715884105727\r\n(-12\r\n:7\r\n");
+        assert_eq!(lexer.next().unwrap().unwrap().as_i128(), Some(i128::MAX));
+        assert_eq!(lexer.next().unwrap().unwrap().as_i128(), Some(-12));
+        assert_eq!(lexer.next().unwrap().unwrap().as_i128(), Some(7));
+
+        let mut lexer = Lexer::new("(3492890328409238509324850943850943825024385\r\n");
+        assert_eq!(lexer.next().unwrap().unwrap().as_i128(), None);
+        assert_eq!(Token::<str>::Double("1.5").as_i128(), None);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_as_bigint() {
+        use num_bigint::BigInt;
+
+        let mut lexer = Lexer::new("(1234567890123456789012345678901234567890\r\n(-12\r\n:7\r\n");
+        let expected: BigInt = "1234567890123456789012345678901234567890".parse().unwrap();
         assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::Array(Some(vec![
+            lexer.next().unwrap().unwrap().as_bigint(),
+            Some(Ok(expected))
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap().as_bigint(),
+            Some(Ok(BigInt::from(-12)))
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap().as_bigint(),
+            Some(Ok(BigInt::from(7)))
+        );
+        assert_eq!(Token::<str>::Double("1.5").as_bigint(), None);
+        assert!(Token::<str>::BigNumber("12a").as_bigint().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_map_pairs() {
+        let mut lexer = Lexer::new("%0\r\n:9\r\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::Map(Some(vec![])))));
+        assert_eq!(lexer.position(), 4);
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(9))));
+
+        let mut lexer = Lexer::new("%2\r\n+a\r\n:1\r\n+b\r\n*1\r\n:2\r\n:9\r\n");
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::Map(Some(vec![
+                Token::SimpleString("a"),
                 Token::Integer(1),
-                Token::Integer(2),
-                Token::Integer(3),
-            ])))
+                Token::SimpleString("b"),
+                Token::Array(Some(vec![Token::Integer(2)])),
+            ]))))
         );
+        assert_eq!(lexer.position(), 24);
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(9))));
+
+        // 只有key没有value
+        let mut lexer = Lexer::new("%1\r\n+a\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+
+        let mut lexer = Lexer::new("|1\r\n+a\r\n:1\r\n:9\r\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(9))));
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(Token::<str>::Integer(1).depth(), 1);
+        assert_eq!(Token::<str>::Array(Some(vec![])).depth(), 1);
+
+        let mut lexer = Lexer::new("*2\r\n:1\r\n*1\r\n*1\r\n:2\r\n");
+        assert_eq!(lexer.next().unwrap().unwrap().depth(), 4);
+
+        let mut lexer = Lexer::new("*1\r\n*1\r\n:5\r\n");
+        assert_eq!(lexer.next().unwrap().unwrap().depth(), 3);
+
+        let mut lexer = Lexer::new("%1\r\n+a\r\n~1\r\n:1\r\n");
+        assert_eq!(lexer.next().unwrap().unwrap().depth(), 3);
+    }
+
+    #[test]
+    fn test_skip_keepalive_crlf() {
+        // 服务端用来保活的空行, 默认严格模式下报错
+        let mut lexer = Lexer::new("\r\n+OK\r\n");
+        assert_eq!(lexer.next(), Some(Err(Error::UnknownType('\r'))));
+
+        let options = ParserBuilder::new()
+            .skip_whitespace_between_frames(true)
+            .options();
+        let mut lexer = Lexer::with_options("\r\n+OK\r\n\r\n\r\n:1\r\n\r\n", options);
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString("OK"))));
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(1))));
         assert_eq!(lexer.next(), None);
+
+        // parser判断buffer是否只剩空白时和lexer跳过的字符一致, \x0c不算空白
+        let parser = Parser::with_options("+OK\r\n \t\r\n", options);
+        assert_eq!(parser.parse_strict(), Ok(vec![Token::SimpleString("OK")]));
+        assert_eq!(parser.count_frames(), Ok(1));
+        let parser = Parser::with_options("+OK\r\n\x0c", options);
+        assert_eq!(parser.parse_strict(), Err(Error::UnknownType('\x0c')));
+        assert_eq!(parser.count_frames(), Err(Error::UnknownType('\x0c')));
+        assert_eq!(
+            Parser::new("\x0c").parse_one(),
+            Err(Error::UnknownType('\x0c'))
+        );
     }
 
     #[test]
-    fn test_array_4() {
-        let mut lexer = Lexer::new("*5\r\n:1\r\n:2\r\n:3\r\n:4\r\n$6\r\nfoobar\r\n");
+    fn test_error_display() {
+        let int_error = "x".parse::<i64>().unwrap_err();
+        let float_error = "x".parse::<f64>().unwrap_err();
+        let bytes = b"\xff".to_vec();
+        let utf8_error = core::str::from_utf8(&bytes).unwrap_err();
+        let cases = [
+            (
+                Error::I64(int_error),
+                "invalid integer: invalid digit found in string",
+            ),
+            (
+                Error::F64(float_error),
+                "invalid double: invalid float literal",
+            ),
+            (
+                Error::Boolean('x'),
+                "invalid boolean byte 'x', expected 't' or 'f'",
+            ),
+            (Error::InvalidLength(-5), "invalid length -5"),
+            (
+                Error::CountOverflow(i64::MAX),
+                "length 9223372036854775807 overflows",
+            ),
+            (Error::IntegerEmpty, "integer has no digits"),
+            (Error::BigNumberEmpty, "big number has no digits"),
+            (
+                Error::Utf8(utf8_error),
+                "invalid utf-8: invalid utf-8 sequence of 1 bytes from index 0",
+            ),
+            (Error::Incomplete, "incomplete frame, more data needed"),
+            (Error::EmptyInput, "empty input"),
+            (Error::UnknownType('?'), "unknown type byte '?'"),
+            (Error::MalformedVerbatim, "malformed verbatim string"),
+            (
+                Error::MissingTerminator,
+                "payload is not followed by \\r\\n",
+            ),
+            (
+                Error::DepthExceeded(2),
+                "nesting exceeds the maximum depth of 2",
+            ),
+            (
+                Error::Resp3NotAllowed('#'),
+                "RESP3 type '#' is not allowed in RESP2 mode",
+            ),
+            (
+                Error::BulkTooLarge(10),
+                "bulk length 10 exceeds the configured maximum",
+            ),
+            (Error::MalformedEofMark, "EOF mark is not 40 bytes"),
+            (
+                Error::LineTooLong(8),
+                "line exceeds the maximum length of 8",
+            ),
+            (Error::MalformedInline, "malformed inline command"),
+            (
+                Error::ElementUtf8 {
+                    index: 1,
+                    error: utf8_error,
+                },
+                "invalid utf-8 in element 1: invalid utf-8 sequence of 1 bytes from index 0",
+            ),
+            (
+                Error::TypeMismatch {
+                    expected: "integer",
+                    found: "array",
+                },
+                "expected integer, found array",
+            ),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_error_source() {
+        use std::error::Error as _;
+
+        let error = Error::I64("x".parse::<i64>().unwrap_err());
+        assert!(error.source().is_some());
+        assert!(Error::Incomplete.source().is_none());
         assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::Array(Some(vec![
+            Error::Io(std::io::ErrorKind::UnexpectedEof).to_string(),
+            "io error: unexpected end of file"
+        );
+
+        let boxed: Box<dyn std::error::Error> = Box::new(Error::Incomplete);
+        assert_eq!(boxed.to_string(), "incomplete frame, more data needed");
+    }
+
+    #[test]
+    fn test_nested_push_and_attribute() {
+        // map的value是一个push, key前面带有attribute
+        let data = "%2\r\n+a\r\n>2\r\n+invalidate\r\n*1\r\n$3\r\nfoo\r\n|1\r\n+ttl\r\n:3\r\n+b\r\n:1\r\n:9\r\n";
+        let mut lexer = Lexer::new(data);
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::Map(Some(vec![
+                Token::SimpleString("a"),
+                Token::Push(Some(vec![
+                    Token::SimpleString("invalidate"),
+                    Token::Array(Some(vec![Token::BulkString(Some("foo"))])),
+                ])),
+                Token::SimpleString("b"),
                 Token::Integer(1),
-                Token::Integer(2),
-                Token::Integer(3),
-                Token::Integer(4),
-                Token::BulkString(Some("foobar")),
-            ])))
+            ]))))
         );
-        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(9))));
+
+        // visit走的是另一条路径, 也要消耗同样的字节
+        assert_eq!(Parser::new(data).count_frames(), Ok(2));
     }
 
     #[test]
-    fn test_array_5() {
-        let mut lexer = Lexer::new("*-1\r\n\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Array(None)));
-        assert_eq!(lexer.next(), None);
+    fn test_as_number() {
+        let mut lexer = Lexer::new(":3\r\n,3\r\n,3.0\r\n,3.5\r\n,-1e3\r\n,1e19\r\n+3\r\n");
+        let tokens: Vec<_> = lexer.by_ref().map(Result::unwrap).collect();
+
+        let floats: Vec<_> = tokens.iter().map(Token::as_number_f64).collect();
+        assert_eq!(
+            floats,
+            vec![
+                Some(3.0),
+                Some(3.0),
+                Some(3.0),
+                Some(3.5),
+                Some(-1000.0),
+                Some(1e19),
+                None
+            ]
+        );
+
+        let integers: Vec<_> = tokens.iter().map(Token::as_number_i64).collect();
+        assert_eq!(
+            integers,
+            vec![Some(3), Some(3), Some(3), None, Some(-1000), None, None]
+        );
+        assert_eq!(
+            Token::<str>::Integer(i64::MAX).as_number_i64(),
+            Some(i64::MAX)
+        );
+        assert_eq!(
+            Token::<str>::Double("inf").as_number_f64(),
+            Some(f64::INFINITY)
+        );
+        assert_eq!(Token::<str>::Double("inf").as_number_i64(), None);
+        assert_eq!(Token::<str>::Double("nan").as_number_i64(), None);
     }
 
     #[test]
-    fn test_boolean() {
-        let mut lexer = Lexer::new("#t\r\n#f\r\n#\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Boolean(true)));
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Boolean(false)));
-        assert_eq!(lexer.next(), None);
+    fn test_resync() {
+        let mut lexer = Lexer::new("+OK\r\n,abc\r\n:1\r\n#x\r\n?foo\r\n+PONG\r\n$3");
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        while let Some(result) = lexer.next() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(Error::Incomplete) => break,
+                Err(e) => {
+                    assert!(!e.is_fatal());
+                    errors.push(e);
+                    assert!(lexer.resync());
+                }
+            }
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                Token::SimpleString("OK"),
+                Token::Integer(1),
+                Token::SimpleString("PONG"),
+            ]
+        );
+        assert_eq!(errors.len(), 3);
+        assert!(!lexer.resync());
+
+        assert!(Error::MissingTerminator.is_fatal());
+        assert!(Error::InvalidLength(-2).is_fatal());
+        assert!(Error::CountOverflow(i64::MAX).is_fatal());
+        assert!(!Error::Incomplete.is_fatal());
     }
 
     #[test]
-    fn test_double() {
-        let mut lexer = Lexer::new(",3.14\r\n,-3.14\r\n,5.9e3\r\n,2\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("3.14")));
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("-3.14")));
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("5.9e3")));
-        assert_eq!(lexer.next().unwrap(), Ok(Token::Double("2")));
-        assert_eq!(lexer.next(), None);
+    fn test_next_raw_integer_text() {
+        let mut lexer = Lexer::new(":+007\r\n*1\r\n:-01\r\n");
+        assert_eq!(lexer.next_raw(), Some((Ok(Token::Integer(7)), ":+007\r\n")));
+        assert_eq!(
+            lexer.next_raw(),
+            Some((
+                Ok(Token::Array(Some(vec![Token::Integer(-1)]))),
+                "*1\r\n:-01\r\n"
+            ))
+        );
     }
 
     #[test]
-    fn test_big_number() {
-        let mut lexer = Lexer::new("(123\r\n(-123\r\n(+123\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::BigNumber("123")));
-        assert_eq!(lexer.next().unwrap(), Ok(Token::BigNumber("-123")));
-        assert_eq!(lexer.next().unwrap(), Ok(Token::BigNumber("+123")));
+    fn test_empty_line_text() {
+        let mut lexer = Lexer::new("+\r\n-\r\n+\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString(""))));
+        assert_eq!(lexer.next(), Some(Ok(Token::Error(""))));
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString(""))));
         assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new(&b"+\r\n"[..]);
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString(&b""[..]))));
     }
 
     #[test]
-    fn test_big_error() {
-        let mut lexer = Lexer::new("!OK\r\n");
-        assert_eq!(lexer.next().unwrap(), Ok(Token::BigErr("OK")));
-        assert_eq!(lexer.next(), None);
+    fn test_max_line_len() {
+        let options = ParserBuilder::new().max_line_len(4).options();
+        let mut lexer = Lexer::with_options("+OK\r\n-ABCD\r\n+hello world", options);
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString("OK"))));
+        assert_eq!(lexer.next(), Some(Ok(Token::Error("ABCD"))));
+        assert_eq!(lexer.next(), Some(Err(Error::LineTooLong(4))));
+
+        // 没有换行符的超长数据也会报错, 不会当作不完整一直等待
+        let mut lexer = Lexer::with_options(&b"-ERRORS"[..], options);
+        assert_eq!(lexer.next(), Some(Err(Error::LineTooLong(4))));
+
+        let mut lexer = Lexer::new("+hello world\r\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString("hello world"))));
     }
 
     #[test]
-    fn test_verbatim_string() {
-        let mut lexer = Lexer::new("=15\r\ntxt:Some string\r\n");
+    fn test_set_protocol() {
+        let buf = "*1\r\n:1\r\n%1\r\n+a\r\n#t\r\n";
+        let options = ParserBuilder::new().protocol(Protocol::Resp2).options();
+        let mut lexer = Lexer::with_options(buf, options);
         assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::VerbatimString("txt", "Some string"))
+            lexer.next(),
+            Some(Ok(Token::Array(Some(vec![Token::Integer(1)]))))
+        );
+
+        // 还没切换之前map会被拒绝
+        assert_eq!(lexer.clone().next(), Some(Err(Error::Resp3NotAllowed('%'))));
+
+        lexer.set_protocol(Protocol::Resp3);
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::Map(Some(vec![
+                Token::SimpleString("a"),
+                Token::Boolean(true),
+            ]))))
         );
-        assert_eq!(lexer.next(), None);
     }
 
     #[test]
-    fn test_set() {
-        let mut lexer = Lexer::new("~2\r\n+hello\r\n+world\r\n");
+    fn test_skip_frame() {
+        let mut lexer =
+            Lexer::new(">3\r\n+message\r\n+news\r\n*2\r\n:1\r\n%1\r\n+a\r\n:2\r\n+OK\r\n");
+        assert_eq!(lexer.skip_frame(), Ok(41));
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString("OK"))));
+        assert_eq!(lexer.skip_frame(), Err(Error::Incomplete));
+
+        let mut lexer = Lexer::new("*2\r\n:1\r\n");
+        assert_eq!(lexer.skip_frame(), Err(Error::Incomplete));
+
+        let mut lexer = Lexer::new("*?\r\n:1\r\n*?\r\n.\r\n.\r\n+OK\r\n");
+        assert_eq!(lexer.skip_frame(), Ok(18));
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString("OK"))));
+    }
+
+    #[test]
+    fn test_hash() {
+        use crate::value::Value;
+        use std::collections::HashSet;
+
+        let buf = "*2\r\n%1\r\n+a\r\n:1\r\n$3\r\nfoo\r\n*2\r\n%1\r\n+a\r\n:1\r\n$3\r\nfoo\r\n*1\r\n:1\r\n";
+        let tokens: HashSet<_> = Lexer::new(buf).map(Result::unwrap).collect();
+        assert_eq!(tokens.len(), 2);
+
+        let values: HashSet<_> = Lexer::new(buf)
+            .map(|token| Value::try_from(token.unwrap()).unwrap())
+            .collect();
+        assert_eq!(values.len(), 2);
+    }
+
+    #[test]
+    fn test_try_as_utf8_array() {
+        let mut lexer = Lexer::new(&b"*2\r\n$3\r\nfoo\r\n$0\r\n\r\n~1\r\n$1\r\na\r\n"[..]);
         assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::Set(Some(vec![
-                Token::SimpleString("hello"),
-                Token::SimpleString("world"),
-            ])))
+            lexer.next().unwrap().unwrap().try_as_utf8_array(),
+            Ok(vec!["foo", ""])
         );
+        assert_eq!(
+            lexer.next().unwrap().unwrap().try_as_utf8_array(),
+            Ok(vec!["a"])
+        );
+
+        let mut lexer = Lexer::new(&b"*3\r\n$1\r\na\r\n$2\r\n\xfa\xfb\r\n$1\r\nb\r\n"[..]);
+        match lexer.next().unwrap().unwrap().try_as_utf8_array() {
+            Err(Error::ElementUtf8 { index, error }) => {
+                assert_eq!(index, 1);
+                assert_eq!(error.valid_up_to(), 0);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let mut lexer = Lexer::new(&b"*1\r\n:1\r\n:1\r\n"[..]);
+        assert_eq!(
+            lexer.next().unwrap().unwrap().try_as_utf8_array(),
+            Err(Error::TypeMismatch {
+                expected: "bulk_string",
+                found: "integer",
+            })
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap().try_as_utf8_array(),
+            Err(Error::TypeMismatch {
+                expected: "array",
+                found: "integer",
+            })
+        );
+    }
+
+    #[test]
+    fn test_big_number_sign() {
+        let mut lexer = Lexer::new("(+123\r\n(-123\r\n(+\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token, Token::BigNumber("+123"));
+        assert_eq!(token.as_i128(), Some(123));
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token, Token::BigNumber("-123"));
+        assert_eq!(token.as_i128(), Some(-123));
+        assert_eq!(lexer.next(), Some(Err(Error::BigNumberEmpty)));
+
+        let mut lexer = Lexer::new("(+");
+        assert_eq!(lexer.next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_big_number_sign_bigint() {
+        use num_bigint::BigInt;
+
+        let mut lexer = Lexer::new("(+123\r\n(-123\r\n(+\r\n");
+        assert_eq!(
+            lexer.next().unwrap().unwrap().as_bigint(),
+            Some(Ok(BigInt::from(123)))
+        );
+        assert_eq!(
+            lexer.next().unwrap().unwrap().as_bigint(),
+            Some(Ok(BigInt::from(-123)))
+        );
+        assert_eq!(lexer.next(), Some(Err(Error::BigNumberEmpty)));
+        // 手动构造的只有符号的token也不能解析成功
+        assert!(Token::<str>::BigNumber("+").as_bigint().unwrap().is_err());
+        assert!(Token::<str>::BigNumber("-").as_bigint().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_empty_input() {
+        let parser = Parser::new("");
+        assert_eq!(parser.parse_strict(), Ok(vec![]));
+        assert_eq!(parser.parse_one(), Err(Error::EmptyInput));
+        assert_eq!(Parser::new("\r\n").parse_one(), Err(Error::EmptyInput));
+
+        let parser = Parser::new("+OK\r\n:1\r\n");
+        assert_eq!(parser.parse_one(), Ok(Token::SimpleString("OK")));
+        assert_eq!(Parser::new("+OK").parse_one(), Err(Error::Incomplete));
+    }
+
+    #[test]
+    fn test_simple_string_then_integer() {
+        let mut lexer = Lexer::new("+OK\r\n:5\r\n");
+        assert_eq!(lexer.next(), Some(Ok(Token::SimpleString("OK"))));
+        assert_eq!(lexer.position(), 5);
+        assert_eq!(lexer.peek_type(), Some(':'));
+        assert_eq!(lexer.next(), Some(Ok(Token::Integer(5))));
+        assert_eq!(lexer.position(), 9);
         assert_eq!(lexer.next(), None);
     }
 
     #[test]
-    fn test_map() {
-        let mut lexer = Lexer::new("%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n");
+    fn test_validate() {
         assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::Map(Some(vec![
-                Token::SimpleString("first"),
-                Token::Integer(1),
-                Token::SimpleString("second"),
-                Token::Integer(2),
-            ])))
+            Parser::new("+OK\r\n*2\r\n:1\r\n%1\r\n+a\r\n#t\r\n").validate(),
+            Ok(())
+        );
+        assert_eq!(Parser::new("").validate(), Ok(()));
+        assert_eq!(
+            Parser::new("+OK\r\n*2\r\n:x\r\n:2\r\n").validate(),
+            Err(Error::IntegerEmpty)
+        );
+        assert_eq!(
+            Parser::new("+OK\r\n$5\r\nhel").validate(),
+            Err(Error::Incomplete)
+        );
+        assert_eq!(Parser::new(":1\r\n:2").validate(), Err(Error::Incomplete));
+
+        assert_eq!(Parser::new("*?\r\n:1\r\n.\r\n").validate(), Ok(()));
+        assert_eq!(
+            Parser::new("%?\r\n+a\r\n.\r\n").validate(),
+            Parser::new("%?\r\n+a\r\n.\r\n").parse_strict().map(|_| ())
+        );
+        assert_eq!(
+            Parser::new("*?\r\n:x\r\n.\r\n").validate(),
+            Err(Error::IntegerEmpty)
+        );
+    }
+
+    #[test]
+    fn test_keep_attributes() {
+        let buf =
+            "|1\r\n+key-popularity\r\n%1\r\n$1\r\na\r\n,0.19\r\n*2\r\n:2039\r\n:9543\r\n+OK\r\n";
+        let attribute = vec![
+            Token::SimpleString("key-popularity"),
+            Token::Map(Some(vec![
+                Token::BulkString(Some("a")),
+                Token::Double("0.19"),
+            ])),
+        ];
+        let array = Token::Array(Some(vec![Token::Integer(2039), Token::Integer(9543)]));
+
+        // 默认跳过attribute
+        let mut lexer = Lexer::new(buf);
+        assert_eq!(lexer.next(), Some(Ok(array.clone())));
+
+        let options = ParserBuilder::new().keep_attributes(true).options();
+        let mut lexer = Lexer::with_options(buf, options);
+        assert_eq!(lexer.next(), Some(Ok(Token::Attribute(attribute.clone()))));
+        assert_eq!(lexer.next(), Some(Ok(array.clone())));
+
+        let mut lexer = Lexer::new(buf);
+        let pairs: Vec<_> = lexer.with_attributes().collect();
+        assert_eq!(
+            pairs,
+            vec![
+                Ok((Some(attribute), array)),
+                Ok((None, Token::SimpleString("OK"))),
+            ]
+        );
+
+        // 聚合类型里的attribute仍然跳过
+        let mut lexer = Lexer::with_options("*1\r\n|1\r\n+a\r\n:1\r\n:2\r\n", options);
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::Array(Some(vec![Token::Integer(2)]))))
+        );
+
+        let mut lexer = Lexer::new("|1\r\n+a\r\n:1\r\n");
+        assert_eq!(lexer.with_attributes().next(), Some(Err(Error::Incomplete)));
+    }
+
+    #[test]
+    fn test_resp_type() {
+        let types = [
+            (b'+', RespType::SimpleString),
+            (b'-', RespType::Error),
+            (b':', RespType::Integer),
+            (b'$', RespType::BulkString),
+            (b'*', RespType::Array),
+            (b'~', RespType::Set),
+            (b'%', RespType::Map),
+            (b'#', RespType::Boolean),
+            (b',', RespType::Double),
+            (b'(', RespType::BigNumber),
+            (b'!', RespType::BigError),
+            (b'=', RespType::VerbatimString),
+            (b'_', RespType::Null),
+            (b'>', RespType::Push),
+            (b'|', RespType::Attribute),
+        ];
+        for (byte, resp_type) in types {
+            assert_eq!(RespType::from_byte(byte), Some(resp_type));
+        }
+        assert_eq!(RespType::from_byte(b'x'), None);
+        assert_eq!(RespType::from_byte(b'\r'), None);
+
+        // 解析出来的token和第一个字节的类型一致
+        let buf = "+OK\r\n-ERR\r\n:1\r\n$1\r\na\r\n*0\r\n~0\r\n%0\r\n#t\r\n,1.5\r\n(1\r\n!1\r\na\r\n=5\r\ntxt:a\r\n_\r\n>0\r\n";
+        let mut lexer = Lexer::new(buf);
+        while let Some(ty) = lexer.peek_type() {
+            let token = lexer.next().unwrap().unwrap();
+            assert_eq!(RespType::from_byte(ty as u8), Some(token.resp_type()));
+        }
+
+        assert_eq!(
+            Token::<str>::StreamedString(vec![]).resp_type(),
+            RespType::BulkString
+        );
+        assert_eq!(Token::<str>::ArrayHeader(1).resp_type(), RespType::Array);
+        assert_eq!(
+            Token::<str>::Attribute(vec![]).resp_type(),
+            RespType::Attribute
+        );
+    }
+}
+
+// 字节lexer的测试, 和&str共用同一套扫描逻辑, 这里只覆盖非utf8数据相关的行为
+#[cfg(test)]
+mod byte_tests {
+    use super::{Error, Lexer, ParserBuilder, Token};
+
+    #[test]
+    fn test_binary_bulk_string() {
+        let mut lexer = Lexer::new(&b"$4\r\n\xff\r\n\x00\r\n$0\r\n\r\n"[..]);
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::BulkString(Some(b"\xff\r\n\x00".as_slice()))))
+        );
+        assert_eq!(
+            lexer.next(),
+            Some(Ok(Token::BulkString(Some(b"".as_slice()))))
         );
         assert_eq!(lexer.next(), None);
     }
 
     #[test]
-    fn test_push() {
-        let mut lexer = Lexer::new(">4\r\n+first\r\n:1\r\n+second\r\n:2\r\n");
+    fn test_non_utf8_text() {
+        let mut lexer = Lexer::new(&b"+\xfe\xff\r\n-ERR \xc3\r\n"[..]);
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token, Token::SimpleString(b"\xfe\xff".as_slice()));
+        assert!(token.try_as_str().unwrap().is_err());
+        assert_eq!(lexer.next(), Some(Ok(Token::Error(b"ERR \xc3".as_slice()))));
+
+        let options = ParserBuilder::new().strict_utf8(true).options();
+        let mut lexer = Lexer::with_options(&b"+\xfe\xff\r\n"[..], options);
+        assert!(matches!(lexer.next(), Some(Err(Error::Utf8(_)))));
+    }
+
+    #[test]
+    fn test_aggregates() {
+        let mut lexer = Lexer::new(&b"*2\r\n$1\r\n\x80\r\n%1\r\n+k\r\n:1\r\n"[..]);
         assert_eq!(
-            lexer.next().unwrap(),
-            Ok(Token::Push(Some(vec![
-                Token::SimpleString("first"),
-                Token::Integer(1),
-                Token::SimpleString("second"),
-                Token::Integer(2),
-            ])))
+            lexer.next(),
+            Some(Ok(Token::Array(Some(vec![
+                Token::BulkString(Some(b"\x80".as_slice())),
+                Token::Map(Some(vec![
+                    Token::SimpleString(b"k".as_slice()),
+                    Token::Integer(1),
+                ])),
+            ]))))
         );
         assert_eq!(lexer.next(), None);
     }
+
+    #[test]
+    fn test_incomplete() {
+        for data in [&b"$4\r\n\xff\xff"[..], b"+\xff", b"*2\r\n$1\r\n\x80\r\n"] {
+            let mut lexer = Lexer::new(data);
+            assert_eq!(lexer.next(), Some(Err(Error::Incomplete)), "{:?}", data);
+        }
+    }
 }
@@ -0,0 +1,182 @@
+use std::io::{self, BufRead, Read};
+
+use crate::chunked::ChunkedParser;
+use crate::parser::{Error, Lexer, ParseOptions};
+use crate::value::Value;
+
+// 从socket之类的BufRead中读取数据, 一个frame跨多次读取时会先缓存起来
+#[derive(Debug)]
+pub struct ReadParser<R> {
+    reader: R,
+    chunks: ChunkedParser,
+}
+
+impl<R: BufRead> ReadParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    // 设置max_bulk_len之后, 声明的长度过大的frame不会被缓存
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Self {
+            reader,
+            chunks: ChunkedParser::with_options(options),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for ReadParser<R> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.chunks.next_frame() {
+                return Some(result);
+            }
+
+            let available = match self.reader.fill_buf() {
+                Ok(available) => available,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(Error::Io(e.kind()))),
+            };
+            if available.is_empty() {
+                // 读到EOF时还有残留的数据, 说明最后一个frame不完整
+                if self.chunks.pending() == 0 {
+                    return None;
+                }
+                self.chunks.clear();
+                return Some(Err(Error::Incomplete));
+            }
+            let len = available.len();
+            self.chunks.push(available);
+            self.reader.consume(len);
+        }
+    }
+}
+
+// 抓包文件里首尾相连的frame, 最后一个frame可能只抓到了一半
+#[derive(Debug, PartialEq)]
+pub struct Capture {
+    pub frames: Vec<Value>,
+    // 末尾不完整的frame的原始数据, 没有时为空
+    pub partial: Vec<u8>,
+}
+
+// 读取整个抓包文件, 格式错误的frame直接返回错误
+pub fn parse_resp_file<R: Read>(mut reader: R) -> Result<Capture, Error> {
+    let mut buf = Vec::new();
+    reader
+        .read_to_end(&mut buf)
+        .map_err(|e| Error::Io(e.kind()))?;
+
+    let mut lexer = Lexer::new(&buf[..]);
+    let mut frames = Vec::new();
+    loop {
+        let start = lexer.position();
+        match lexer.next().map(|result| result.and_then(Value::try_from)) {
+            Some(Ok(value)) => frames.push(value),
+            None | Some(Err(Error::Incomplete)) => {
+                return Ok(Capture {
+                    frames,
+                    partial: buf[start..].to_vec(),
+                });
+            }
+            Some(Err(e)) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_resp_file, Capture, ReadParser};
+    use crate::parser::{Error, ParserBuilder};
+    use crate::value::Value;
+    use std::io::{BufReader, Cursor};
+
+    #[test]
+    fn test_read_parser() {
+        let data = b"+OK\r\n:1000\r\n*2\r\n$3\r\nfoo\r\n$3\r\n\xff\x00\x01\r\n".to_vec();
+        // 每次只读3个字节, frame会跨多次读取
+        let reader = BufReader::with_capacity(3, Cursor::new(data));
+        let mut parser = ReadParser::new(reader);
+        assert_eq!(
+            parser.next(),
+            Some(Ok(Value::SimpleString("OK".to_string())))
+        );
+        assert_eq!(parser.next(), Some(Ok(Value::Integer(1000))));
+        assert_eq!(
+            parser.next(),
+            Some(Ok(Value::Array(Some(vec![
+                Value::BulkString(Some(b"foo".to_vec())),
+                Value::BulkString(Some(vec![0xff, 0x00, 0x01])),
+            ]))))
+        );
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn test_read_parser_truncated() {
+        let mut parser = ReadParser::new(Cursor::new(b"+OK\r\n$5\r\nhel".to_vec()));
+        assert_eq!(
+            parser.next(),
+            Some(Ok(Value::SimpleString("OK".to_string())))
+        );
+        assert_eq!(parser.next(), Some(Err(Error::Incomplete)));
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn test_read_parser_max_bulk_len() {
+        let mut data = b"$536870912\r\n".to_vec();
+        data.resize(64 * 1024, b'x');
+        let reader = BufReader::with_capacity(16, Cursor::new(data));
+        let options = ParserBuilder::new().max_bulk_len(1024).options();
+        let mut parser = ReadParser::with_options(reader, options);
+        assert_eq!(parser.next(), Some(Err(Error::BulkTooLarge(536870912))));
+        // 只读了长度前缀所在的那一块
+        assert_eq!(parser.into_inner().into_inner().position(), 16);
+    }
+
+    #[test]
+    fn test_read_parser_verbatim() {
+        let data = b"=15\r\ntxt:Some string\r\n".to_vec();
+        let reader = BufReader::with_capacity(3, Cursor::new(data));
+        let mut parser = ReadParser::new(reader);
+        assert_eq!(
+            parser.next(),
+            Some(Ok(Value::VerbatimString(
+                "txt".to_string(),
+                "Some string".to_string()
+            )))
+        );
+        assert_eq!(parser.next(), None);
+    }
+
+    #[test]
+    fn test_parse_resp_file() {
+        let data = b"+OK\r\n*1\r\n:1\r\n$5\r\nhel".to_vec();
+        assert_eq!(
+            parse_resp_file(Cursor::new(data)),
+            Ok(Capture {
+                frames: vec![
+                    Value::SimpleString("OK".to_string()),
+                    Value::Array(Some(vec![Value::Integer(1)])),
+                ],
+                partial: b"$5\r\nhel".to_vec(),
+            })
+        );
+
+        let capture = parse_resp_file(Cursor::new(b":1\r\n".to_vec())).unwrap();
+        assert_eq!(capture.frames, vec![Value::Integer(1)]);
+        assert!(capture.partial.is_empty());
+
+        assert_eq!(
+            parse_resp_file(Cursor::new(b"#x\r\n".to_vec())),
+            Err(Error::Boolean('x'))
+        );
+    }
+}
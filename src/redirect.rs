@@ -0,0 +1,82 @@
+use crate::parser::Token;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RedirectKind {
+    Moved,
+    Ask,
+}
+
+// 集群返回的 -MOVED 3999 127.0.0.1:6381
+#[derive(Debug, PartialEq)]
+pub struct Redirect<'a> {
+    pub kind: RedirectKind,
+    pub slot: u16,
+    pub address: &'a str,
+}
+
+impl<'a> Token<'a> {
+    pub fn redirect(&self) -> Option<Redirect<'a>> {
+        let (code, message) = self.split_error()?;
+        let kind = match code {
+            "MOVED" => RedirectKind::Moved,
+            "ASK" => RedirectKind::Ask,
+            _ => return None,
+        };
+        let (slot, address) = message.split_once(' ')?;
+        let slot = slot.parse().ok()?;
+        if address.is_empty() || address.contains(' ') {
+            return None;
+        }
+        Some(Redirect {
+            kind,
+            slot,
+            address,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Redirect, RedirectKind};
+    use crate::parser::{Lexer, Token};
+
+    #[test]
+    fn test_moved() {
+        let mut lexer = Lexer::new("-MOVED 3999 127.0.0.1:6381\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token.error_code(), Some("MOVED"));
+        assert_eq!(
+            token.redirect(),
+            Some(Redirect {
+                kind: RedirectKind::Moved,
+                slot: 3999,
+                address: "127.0.0.1:6381",
+            })
+        );
+    }
+
+    #[test]
+    fn test_ask() {
+        let mut lexer = Lexer::new("-ASK 3999 127.0.0.1:6381\r\n");
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(
+            token.redirect(),
+            Some(Redirect {
+                kind: RedirectKind::Ask,
+                slot: 3999,
+                address: "127.0.0.1:6381",
+            })
+        );
+    }
+
+    #[test]
+    fn test_not_redirect() {
+        assert_eq!(Token::Error("ERR unknown command").redirect(), None);
+        assert_eq!(Token::Error("MOVED abc 127.0.0.1:6381").redirect(), None);
+        assert_eq!(Token::Error("MOVED 3999").redirect(), None);
+        assert_eq!(
+            Token::SimpleString("MOVED 3999 127.0.0.1:6381").redirect(),
+            None
+        );
+    }
+}
@@ -0,0 +1,566 @@
+use alloc::vec::Vec;
+
+use crate::parser::{
+    is_frame_whitespace, line_terminator, payload_terminator, Error, Lexer, ParseOptions,
+    EOF_MARK_LEN,
+};
+use crate::value::Value;
+
+// 还没有读完的聚合类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pending {
+    // 还差多少个元素, None表示长度未知, 读到 .\r\n 为止
+    Aggregate {
+        remaining: Option<usize>,
+        attribute: bool,
+    },
+    // $?\r\n 后面的chunk, 读到 ;0\r\n 为止
+    Chunks,
+}
+
+// 数据用完时停在哪一步, 下次feed之后从这里继续
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    // 等待一行完整的header或者单行的frame, 前scanned个字节里已经确认没有换行符
+    Line { scanned: usize },
+    // 正在读定长的body, 一共len个字节(不包括结尾的\r\n)
+    Body { len: usize },
+    // body已经读完, 等待结尾的\r\n
+    BodyEnd,
+    // $EOF:之后的payload, 一直读到mark位置上的40字节分隔符再次出现,
+    // 前scanned个字节里已经确认没有分隔符
+    EofMarked { mark: usize, scanned: usize },
+}
+
+// 和ChunkedParser一样按块接收数据, 但是数据不够时会记住当前的位置,
+// 后续的feed不会从frame的开头重新扫描, 一个很大的bulk string逐字节到达时也只解析一次
+// 只有确认frame已经完整之后才交给lexer生成Value
+#[derive(Debug, Clone)]
+pub struct ResumableParser {
+    buf: Vec<u8>,
+    // 当前frame在buf里的起始位置, 前面是已经返回过的frame
+    start: usize,
+    // 当前frame里已经确认完整的位置
+    cursor: usize,
+    state: State,
+    stack: Vec<Pending>,
+    options: ParseOptions,
+}
+
+impl Default for ResumableParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResumableParser {
+    pub fn new() -> Self {
+        Self::with_options(ParseOptions::default())
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            buf: Vec::new(),
+            start: 0,
+            cursor: 0,
+            state: State::Line { scanned: 0 },
+            stack: Vec::new(),
+            options,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    // 还没有组成完整frame的字节数
+    pub fn pending(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.start = 0;
+        self.rewind();
+    }
+
+    // 正在读bulk string之类的body时, 还差多少个字节(包括结尾的\r\n)
+    pub fn body_remaining(&self) -> Option<usize> {
+        let available = self.buf.len() - self.cursor;
+        match self.state {
+            State::Body { len } => Some(len.saturating_sub(available) + 2),
+            State::BodyEnd => Some(2usize.saturating_sub(available)),
+            State::Line { .. } | State::EofMarked { .. } => None,
+        }
+    }
+
+    // 返回下一个完整的frame, 数据不够时返回None
+    pub fn next_frame(&mut self) -> Option<Result<Value, Error>> {
+        loop {
+            let done = match self.state {
+                State::Line { scanned } => {
+                    if self.cursor == self.start && self.options.skip_whitespace_between_frames {
+                        let skipped = self.buf[self.cursor..]
+                            .iter()
+                            .take_while(|c| is_frame_whitespace(c))
+                            .count();
+                        self.cursor += skipped;
+                        self.start += skipped;
+                    }
+                    let line = &self.buf[self.cursor..];
+                    let Some(end) = line[scanned..]
+                        .iter()
+                        .position(|c| *c == b'\r' || *c == b'\n')
+                        .map(|end| scanned + end)
+                    else {
+                        if self
+                            .options
+                            .max_line_len
+                            .is_some_and(|max| line.len() > max + 1)
+                        {
+                            return self.lex();
+                        }
+                        self.state = State::Line {
+                            scanned: line.len(),
+                        };
+                        return None;
+                    };
+                    // 和lexer一样, 行尾可以是\r\n或者单独的\n
+                    let terminator = match line_terminator(&line[end..]) {
+                        Ok(terminator) => terminator,
+                        Err(Error::Incomplete) => {
+                            self.state = State::Line { scanned: end };
+                            return None;
+                        }
+                        Err(_) => return self.lex(),
+                    };
+                    let start = self.cursor;
+                    self.cursor += end + terminator;
+                    self.state = State::Line { scanned: 0 };
+                    match self.step(start, start + end) {
+                        Some(done) => done,
+                        // 格式不对, 交给lexer报告具体的错误
+                        None => return self.lex(),
+                    }
+                }
+                State::Body { len } => {
+                    if self.buf.len() - self.cursor < len {
+                        return None;
+                    }
+                    self.cursor += len;
+                    self.state = State::BodyEnd;
+                    continue;
+                }
+                State::BodyEnd => {
+                    match payload_terminator(&self.buf[self.cursor..]) {
+                        Ok(terminator) => self.cursor += terminator,
+                        Err(Error::Incomplete) => return None,
+                        Err(_) => return self.lex(),
+                    }
+                    self.state = State::Line { scanned: 0 };
+                    match self.stack.last() {
+                        Some(Pending::Chunks) => false,
+                        _ => self.complete(),
+                    }
+                }
+                State::EofMarked { mark, scanned } => {
+                    let payload = &self.buf[self.cursor..];
+                    // 分隔符可能跨过上次扫描的结尾
+                    let from = scanned.saturating_sub(EOF_MARK_LEN - 1);
+                    let delimiter = &self.buf[mark..mark + EOF_MARK_LEN];
+                    let found = payload[from..]
+                        .windows(EOF_MARK_LEN)
+                        .position(|window| window == delimiter);
+                    let Some(end) = found else {
+                        // 已经确认payload超过了max_bulk_len, 交给lexer报错
+                        if self
+                            .options
+                            .max_bulk_len
+                            .is_some_and(|max| payload.len() >= max + EOF_MARK_LEN)
+                        {
+                            return self.lex();
+                        }
+                        self.state = State::EofMarked {
+                            mark,
+                            scanned: payload.len(),
+                        };
+                        return None;
+                    };
+                    if self
+                        .options
+                        .max_bulk_len
+                        .is_some_and(|max| from + end > max)
+                    {
+                        return self.lex();
+                    }
+                    // 分隔符后面没有\r\n
+                    self.cursor += from + end + EOF_MARK_LEN;
+                    self.state = State::Line { scanned: 0 };
+                    self.complete()
+                }
+            };
+            if done {
+                return self.lex();
+            }
+        }
+    }
+
+    // 处理buf[start..end]这一行, 返回true表示整个frame已经完整, None表示格式不对
+    fn step(&mut self, start: usize, end: usize) -> Option<bool> {
+        let (&ty, rest) = self.buf[start..end].split_first()?;
+        let chunks = self.stack.last() == Some(&Pending::Chunks);
+        if chunks != (ty == b';') {
+            return None;
+        }
+        match ty {
+            b'+' | b'-' | b':' | b'#' | b',' | b'(' | b'_' => Some(self.complete()),
+            b'$' if rest == b"?" => {
+                self.stack.push(Pending::Chunks);
+                Some(false)
+            }
+            b'$' if rest.starts_with(b"EOF:") => {
+                if rest.len() != 4 + EOF_MARK_LEN {
+                    return None;
+                }
+                self.state = State::EofMarked {
+                    mark: start + 5,
+                    scanned: 0,
+                };
+                Some(false)
+            }
+            b'$' | b'=' | b'!' | b';' => match parse_len(rest)? {
+                None => Some(self.complete()),
+                Some(0) if ty == b';' => {
+                    self.stack.pop();
+                    Some(self.complete())
+                }
+                Some(len) if self.options.max_bulk_len.is_some_and(|max| len > max) => None,
+                Some(len) => {
+                    self.state = State::Body { len };
+                    Some(false)
+                }
+            },
+            b'*' | b'~' | b'%' | b'>' if rest == b"?" => {
+                self.stack.push(Pending::Aggregate {
+                    remaining: None,
+                    attribute: false,
+                });
+                Some(false)
+            }
+            b'*' | b'~' | b'%' | b'>' | b'|' => match parse_len(rest)? {
+                None => Some(self.complete()),
+                // streaming模式下lexer只返回最外层数组的长度
+                Some(_) if ty == b'*' && self.options.streaming && self.stack.is_empty() => {
+                    Some(true)
+                }
+                Some(count) => {
+                    let count = match ty {
+                        b'%' | b'|' => count.checked_mul(2)?,
+                        _ => count,
+                    };
+                    Some(self.open(count, ty == b'|'))
+                }
+            },
+            b'.' if rest.is_empty() => match self.stack.pop() {
+                Some(Pending::Aggregate {
+                    remaining: None, ..
+                }) => Some(self.complete()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn open(&mut self, count: usize, attribute: bool) -> bool {
+        if count == 0 {
+            return self.close(attribute);
+        }
+        self.stack.push(Pending::Aggregate {
+            remaining: Some(count),
+            attribute,
+        });
+        false
+    }
+
+    // 一个聚合类型的元素都读完了
+    fn close(&mut self, attribute: bool) -> bool {
+        // attribute不算外层聚合类型的元素
+        if attribute {
+            return self.stack.is_empty() && self.options.keep_attributes;
+        }
+        self.complete()
+    }
+
+    // 读完一个元素, 返回true表示最外层的frame已经完整
+    fn complete(&mut self) -> bool {
+        match self.stack.last_mut() {
+            None => true,
+            Some(Pending::Aggregate {
+                remaining: Some(remaining),
+                attribute,
+            }) => {
+                *remaining -= 1;
+                if *remaining > 0 {
+                    return false;
+                }
+                let attribute = *attribute;
+                self.stack.pop();
+                self.close(attribute)
+            }
+            Some(_) => false,
+        }
+    }
+
+    // 回到当前frame的开头
+    fn rewind(&mut self) {
+        self.cursor = self.start;
+        self.state = State::Line { scanned: 0 };
+        self.stack.clear();
+    }
+
+    // frame已经完整(或者已经确认格式错误), 交给lexer解析一次
+    fn lex(&mut self) -> Option<Result<Value, Error>> {
+        let mut lexer = Lexer::with_options(&self.buf[self.start..], self.options);
+        let result = lexer.next().map(|result| result.and_then(Value::try_from));
+        let consumed = lexer.position();
+        // 不管结果如何, 扫描的状态都不能留在frame中间
+        self.rewind();
+        if result == Some(Err(Error::Incomplete)) {
+            return None;
+        }
+        self.start += consumed;
+        self.cursor = self.start;
+        // 已经返回的frame占了一半以上再整体移动, 每个frame不用都移动一次
+        if self.start * 2 > self.buf.len() {
+            self.buf.drain(..self.start);
+            self.start = 0;
+            self.cursor = 0;
+        }
+        result
+    }
+}
+
+// 长度前缀, -1表示null
+fn parse_len(text: &[u8]) -> Option<Option<usize>> {
+    match core::str::from_utf8(text).ok()?.parse::<i64>().ok()? {
+        -1 => Some(None),
+        len => usize::try_from(len).ok().map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResumableParser;
+    use crate::parser::{Error, ParserBuilder};
+    use crate::value::Value;
+
+    #[test]
+    fn test_feed_bulk_byte_by_byte() {
+        let body = [b'x'; 100];
+        let mut data = b"$100\r\n".to_vec();
+        data.extend_from_slice(&body);
+        data.extend_from_slice(b"\r\n");
+
+        let mut parser = ResumableParser::new();
+        let mut values = Vec::new();
+        for (i, byte) in data.iter().enumerate() {
+            parser.feed(&[*byte]);
+            while let Some(value) = parser.next_frame() {
+                values.push(value.unwrap());
+            }
+            // 读完header之后只记录body还差多少个字节
+            if (6..data.len() - 1).contains(&i) {
+                assert_eq!(parser.body_remaining(), Some(data.len() - 1 - i));
+            }
+        }
+        assert_eq!(values, vec![Value::BulkString(Some(body.to_vec()))]);
+        assert_eq!(parser.body_remaining(), None);
+        assert_eq!(parser.pending(), 0);
+    }
+
+    #[test]
+    fn test_feed_nested_byte_by_byte() {
+        let data = b"*3\r\n$-1\r\n%1\r\n+key\r\n~2\r\n$5\r\nva\r\nl\r\n:1\r\n>?\r\n$?\r\n;2\r\nab\r\n;0\r\n.\r\n\
+                     |1\r\n+ttl\r\n:3\r\n:-3\r\n";
+        let mut parser = ResumableParser::new();
+        let mut values = Vec::new();
+        for byte in data {
+            parser.feed(&[*byte]);
+            while let Some(value) = parser.next_frame() {
+                values.push(value.unwrap());
+            }
+        }
+        assert_eq!(
+            values,
+            vec![
+                Value::Array(Some(vec![
+                    Value::BulkString(None),
+                    Value::Map(Some(vec![
+                        Value::SimpleString("key".to_string()),
+                        Value::Set(Some(vec![
+                            Value::BulkString(Some(b"va\r\nl".to_vec())),
+                            Value::Integer(1),
+                        ])),
+                    ])),
+                    Value::Push(Some(vec![Value::BulkString(Some(b"ab".to_vec()))])),
+                ])),
+                Value::Integer(-3),
+            ]
+        );
+        assert_eq!(parser.pending(), 0);
+    }
+
+    #[test]
+    fn test_feed_options() {
+        let options = ParserBuilder::new()
+            .keep_attributes(true)
+            .skip_whitespace_between_frames(true)
+            .options();
+        let mut parser = ResumableParser::with_options(options);
+        parser.feed(b"\r\n|1\r\n+ttl\r\n:3\r\n\r\n*0");
+        assert_eq!(
+            parser.next_frame(),
+            Some(Ok(Value::Attribute(vec![
+                Value::SimpleString("ttl".to_string()),
+                Value::Integer(3),
+            ])))
+        );
+        assert_eq!(parser.next_frame(), None);
+        parser.feed(b"\r\n");
+        assert_eq!(parser.next_frame(), Some(Ok(Value::Array(Some(vec![])))));
+        assert_eq!(parser.next_frame(), None);
+
+        let options = ParserBuilder::new().max_bulk_len(3).options();
+        let mut parser = ResumableParser::with_options(options);
+        parser.feed(b"$4\r\n");
+        assert_eq!(parser.next_frame(), Some(Err(Error::BulkTooLarge(4))));
+    }
+
+    #[test]
+    fn test_feed_malformed() {
+        let mut parser = ResumableParser::new();
+        parser.feed(b"*2\r\n:1\r\n#x\r\n+OK\r\n");
+        assert_eq!(parser.next_frame(), Some(Err(Error::Boolean('x'))));
+
+        let mut parser = ResumableParser::new();
+        parser.feed(b"$3\r\nfoo");
+        assert_eq!(parser.next_frame(), None);
+        assert_eq!(parser.body_remaining(), Some(2));
+        parser.feed(b"xx");
+        assert_eq!(parser.next_frame(), Some(Err(Error::MissingTerminator)));
+    }
+
+    fn feed_bytes(parser: &mut ResumableParser, data: &[u8]) -> Vec<Value> {
+        let mut values = Vec::new();
+        for byte in data {
+            parser.feed(&[*byte]);
+            while let Some(value) = parser.next_frame() {
+                values.push(value.unwrap());
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn test_feed_lf_only() {
+        // 和lexer一样, 单独的\n也可以结束一行
+        let mut parser = ResumableParser::new();
+        let values = feed_bytes(&mut parser, b"+OK\n:1\n$3\nfoo\r\n*1\n:2\n");
+        assert_eq!(
+            values,
+            vec![
+                Value::SimpleString("OK".to_string()),
+                Value::Integer(1),
+                Value::BulkString(Some(b"foo".to_vec())),
+                Value::Array(Some(vec![Value::Integer(2)])),
+            ]
+        );
+        assert_eq!(parser.pending(), 0);
+
+        let mut parser = ResumableParser::new();
+        parser.feed(b"+OK\rX\r\n");
+        assert_eq!(parser.next_frame(), Some(Err(Error::MissingTerminator)));
+    }
+
+    #[test]
+    fn test_feed_eof_marked() {
+        let mark = b"0123456789abcdef0123456789abcdef01234567";
+        // payload里有分隔符的前缀, 不能当成结尾
+        let data = [
+            b"*2\r\n$EOF:".as_slice(),
+            mark,
+            b"\r\nREDIS0011",
+            &mark[..39],
+            mark,
+            b":1\r\n+OK\r\n",
+        ]
+        .concat();
+        let mut payload = b"REDIS0011".to_vec();
+        payload.extend_from_slice(&mark[..39]);
+
+        let mut parser = ResumableParser::new();
+        let values = feed_bytes(&mut parser, &data);
+        assert_eq!(
+            values,
+            vec![
+                Value::Array(Some(vec![
+                    Value::BulkString(Some(payload)),
+                    Value::Integer(1),
+                ])),
+                Value::SimpleString("OK".to_string()),
+            ]
+        );
+        assert_eq!(parser.pending(), 0);
+
+        // 分隔符迟迟不出现时, 超过max_bulk_len就报错
+        let options = ParserBuilder::new().max_bulk_len(9).options();
+        let mut parser = ResumableParser::with_options(options);
+        parser.feed(&[b"$EOF:".as_slice(), mark, b"\r\nREDIS0011"].concat());
+        assert_eq!(parser.next_frame(), None);
+        let mut result = None;
+        for _ in 0..mark.len() {
+            parser.feed(b"x");
+            result = parser.next_frame();
+            if result.is_some() {
+                break;
+            }
+        }
+        assert_eq!(result, Some(Err(Error::BulkTooLarge(10))));
+
+        // 整个payload一次到达, 分隔符在max_bulk_len之后
+        let mut parser = ResumableParser::with_options(options);
+        parser.feed(&[b"$EOF:".as_slice(), mark, b"\r\nREDIS0011x", mark].concat());
+        assert_eq!(parser.next_frame(), Some(Err(Error::BulkTooLarge(10))));
+    }
+
+    #[test]
+    fn test_feed_error_mid_frame() {
+        let mut parser = ResumableParser::new();
+        parser.feed(b"*3\r\n:1\r\n$3\r\nfoo");
+        assert_eq!(parser.next_frame(), None);
+        assert_eq!(parser.body_remaining(), Some(2));
+        parser.feed(b"xx\r\n");
+        assert_eq!(parser.next_frame(), Some(Err(Error::MissingTerminator)));
+        // 出错之后不会停在数组中间
+        assert_eq!(parser.body_remaining(), None);
+
+        parser.clear();
+        parser.feed(b":2\r\n");
+        assert_eq!(parser.next_frame(), Some(Ok(Value::Integer(2))));
+
+        // streaming模式下map里的数组是一个完整的元素
+        let options = ParserBuilder::new().streaming(true).options();
+        let mut parser = ResumableParser::with_options(options);
+        let values = feed_bytes(&mut parser, b"%1\r\n+a\r\n*2\r\n:1\r\n:2\r\n*1\r\n");
+        assert_eq!(
+            values,
+            vec![
+                Value::Map(Some(vec![
+                    Value::SimpleString("a".to_string()),
+                    Value::Array(Some(vec![Value::Integer(1), Value::Integer(2)])),
+                ])),
+                Value::ArrayHeader(1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::value::Value;
+
+// 约定: map转成对象, array/set/push转成数组, 空的bulk string和aggregate转成null
+// bulk string是合法utf8时转成字符串, 否则用serialize_bytes交给格式自己处理
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::SimpleString(value)
+            | Value::Error(value)
+            | Value::BigNumber(value)
+            | Value::BigErr(value)
+            | Value::VerbatimString(_, value) => serializer.serialize_str(value),
+            Value::Integer(value) => serializer.serialize_i64(*value),
+            Value::BulkString(Some(value)) => match core::str::from_utf8(value) {
+                Ok(value) => serializer.serialize_str(value),
+                Err(_) => serializer.serialize_bytes(value),
+            },
+            Value::Boolean(value) => serializer.serialize_bool(*value),
+            // inf/nan这类没法解析成数字的就保留原文
+            Value::Double(value) => match value.parse::<f64>() {
+                Ok(number) if number.is_finite() => serializer.serialize_f64(number),
+                _ => serializer.serialize_str(value),
+            },
+            Value::Array(Some(values)) | Value::Set(Some(values)) | Value::Push(Some(values)) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            Value::Map(Some(pairs)) | Value::Attribute(pairs) => {
+                let mut map = serializer.serialize_map(Some(pairs.len() / 2))?;
+                for pair in pairs.chunks(2) {
+                    map.serialize_entry(&pair[0], pair.get(1).unwrap_or(&Value::Null))?;
+                }
+                map.end()
+            }
+            Value::ArrayHeader(len) => serializer.serialize_u64(*len as u64),
+            Value::BulkString(None)
+            | Value::Array(None)
+            | Value::Set(None)
+            | Value::Map(None)
+            | Value::Push(None)
+            | Value::Null => serializer.serialize_unit(),
+        }
+    }
+}
+
+// 反序列化只能尽量还原: 字符串都当成bulk string, 对象还原成map
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a RESP value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Integer(value))
+    }
+
+    // 超出i64的整数只能用big number表示
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Value, E> {
+        Ok(match i64::try_from(value) {
+            Ok(value) => Value::Integer(value),
+            Err(_) => Value::BigNumber(value.to_string()),
+        })
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Double(crate::parser::format_double(value)))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::BulkString(Some(value.as_bytes().to_vec())))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Value, E> {
+        Ok(Value::BulkString(Some(value.into_bytes())))
+    }
+
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Value, E> {
+        Ok(Value::BulkString(Some(value.to_vec())))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::BulkString(Some(value)))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(1024));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(Some(values)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut pairs = Vec::with_capacity(map.size_hint().unwrap_or(0).min(512) * 2);
+        while let Some((key, value)) = map.next_entry()? {
+            pairs.push(key);
+            pairs.push(value);
+        }
+        Ok(Value::Map(Some(pairs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::value::{parse_all, Value};
+
+    #[test]
+    fn test_round_trip() {
+        let value = Value::Map(Some(vec![
+            Value::BulkString(Some(b"name".to_vec())),
+            Value::BulkString(Some(b"redis".to_vec())),
+            Value::BulkString(Some(b"list".to_vec())),
+            Value::Array(Some(vec![
+                Value::Integer(-1),
+                Value::Boolean(true),
+                Value::Double("1.5".to_string()),
+                Value::Null,
+                Value::Map(Some(vec![
+                    Value::BulkString(Some(b"inner".to_vec())),
+                    Value::Array(Some(vec![])),
+                ])),
+            ])),
+        ]));
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"redis","list":[-1,true,1.5,null,{"inner":[]}]}"#
+        );
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+    }
+
+    #[test]
+    fn test_serialize_reply() {
+        let values =
+            parse_all(b"%2\r\n+key\r\n$-1\r\n:1\r\n~2\r\n#f\r\n,-2.5\r\n*-1\r\n$2\r\n\xff\xfe\r\n")
+                .unwrap();
+        assert_eq!(
+            serde_json::to_value(&values).unwrap(),
+            serde_json::json!([{"key": null, "1": [false, -2.5]}, null, [255, 254]])
+        );
+    }
+
+    #[test]
+    fn test_deserialize_big_integer() {
+        assert_eq!(
+            serde_json::from_str::<Value>("18446744073709551615").unwrap(),
+            Value::BigNumber("18446744073709551615".to_string())
+        );
+    }
+}
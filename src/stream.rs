@@ -0,0 +1,153 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::chunked::ChunkedParser;
+use crate::parser::{Error, ParseOptions};
+use crate::value::Value;
+
+const READ_SIZE: usize = 4096;
+
+// ReadParser的异步版本, 从AsyncRead里读取数据, frame完整之后才返回
+#[derive(Debug)]
+pub struct AsyncReadParser<R> {
+    reader: R,
+    chunks: ChunkedParser,
+    buf: Box<[u8]>,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncReadParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Self {
+            reader,
+            chunks: ChunkedParser::with_options(options),
+            buf: vec![0; READ_SIZE].into_boxed_slice(),
+            eof: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for AsyncReadParser<R> {
+    type Item = Result<Value, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(result) = this.chunks.next_frame() {
+                return Poll::Ready(Some(result));
+            }
+            if this.eof {
+                return Poll::Ready(None);
+            }
+
+            let mut buf = ReadBuf::new(&mut this.buf);
+            match Pin::new(&mut this.reader).poll_read(cx, &mut buf) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(Error::Io(e.kind())))),
+                Poll::Ready(Ok(())) => {}
+            }
+            let filled = buf.filled();
+            if filled.is_empty() {
+                // 读到EOF时还有残留的数据, 说明最后一个frame不完整
+                this.eof = true;
+                if this.chunks.pending() == 0 {
+                    return Poll::Ready(None);
+                }
+                this.chunks.clear();
+                return Poll::Ready(Some(Err(Error::Incomplete)));
+            }
+            this.chunks.push(filled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    use futures_core::Stream;
+    use tokio::io::{duplex, AsyncRead, AsyncWriteExt};
+
+    use super::AsyncReadParser;
+    use crate::parser::{Error, ParserBuilder};
+    use crate::value::Value;
+
+    async fn collect<R: AsyncRead + Unpin>(
+        parser: &mut AsyncReadParser<R>,
+    ) -> Vec<Result<Value, Error>> {
+        let mut results = Vec::new();
+        while let Some(result) = poll_fn(|cx| Pin::new(&mut *parser).poll_next(cx)).await {
+            results.push(result);
+        }
+        results
+    }
+
+    #[tokio::test]
+    async fn test_partial_writes() {
+        let (mut client, server) = duplex(8);
+        let writer = tokio::spawn(async move {
+            // 每次写入的边界都落在frame中间
+            for part in [
+                b"*2\r\n$3".as_slice(),
+                b"\r\nfoo\r\n:",
+                b"1\r\n+O",
+                b"K\r\n$5\r\nva\r\nl\r\n",
+            ] {
+                client.write_all(part).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        });
+        let mut parser = AsyncReadParser::new(server);
+        assert_eq!(
+            collect(&mut parser).await,
+            vec![
+                Ok(Value::Array(Some(vec![
+                    Value::BulkString(Some(b"foo".to_vec())),
+                    Value::Integer(1),
+                ]))),
+                Ok(Value::SimpleString("OK".to_string())),
+                Ok(Value::BulkString(Some(b"va\r\nl".to_vec()))),
+            ]
+        );
+        writer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_incomplete_at_eof() {
+        let (mut client, server) = duplex(64);
+        client.write_all(b":1\r\n$10\r\nabc").await.unwrap();
+        drop(client);
+        let mut parser = AsyncReadParser::new(server);
+        assert_eq!(
+            collect(&mut parser).await,
+            vec![Ok(Value::Integer(1)), Err(Error::Incomplete)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_options() {
+        let (mut client, server) = duplex(64);
+        client.write_all(b"$536870912\r\n").await.unwrap();
+        let options = ParserBuilder::new().max_bulk_len(1024).options();
+        let mut parser = AsyncReadParser::with_options(server, options);
+        // 只收到长度前缀就返回错误, 不用等写端关闭
+        assert_eq!(
+            poll_fn(|cx| Pin::new(&mut parser).poll_next(cx)).await,
+            Some(Err(Error::BulkTooLarge(536870912)))
+        );
+    }
+}
@@ -0,0 +1,613 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::cmd::write_header;
+use crate::parser::{is_frame_whitespace, Error, Input, Lexer, ParserBuilder, Protocol, Token};
+
+// Token的owned版本, 不再借用原始的buffer
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    SimpleString(String),
+    Error(String),
+    Integer(i64),
+    BulkString(Option<Vec<u8>>),
+    Array(Option<Vec<Value>>),
+    Boolean(bool),
+    Set(Option<Vec<Value>>),
+    Double(String),
+    BigNumber(String),
+    BigErr(String),
+    VerbatimString(String, String),
+    Map(Option<Vec<Value>>),
+    Push(Option<Vec<Value>>),
+    ArrayHeader(usize),
+    Null,
+    Attribute(Vec<Value>),
+}
+
+// 字节lexer解析出来的文本不一定是合法的utf8
+fn text<S: ?Sized + Input>(text: &S) -> Result<String, Error> {
+    core::str::from_utf8(text.as_bytes())
+        .map(String::from)
+        .map_err(Error::Utf8)
+}
+
+fn list<S: ?Sized + Input>(list: Option<Vec<Token<S>>>) -> Result<Option<Vec<Value>>, Error> {
+    list.map(|list| list.into_iter().map(Value::try_from).collect())
+        .transpose()
+}
+
+impl<'a, S: ?Sized + Input> TryFrom<Token<'a, S>> for Value {
+    type Error = Error;
+
+    fn try_from(token: Token<'a, S>) -> Result<Value, Error> {
+        let value = match token {
+            Token::SimpleString(s) => Value::SimpleString(text(s)?),
+            Token::Error(s) => Value::Error(text(s)?),
+            Token::Integer(value) => Value::Integer(value),
+            Token::BulkString(s) => Value::BulkString(s.map(|s| s.as_bytes().to_vec())),
+            Token::Array(l) => Value::Array(list(l)?),
+            Token::Boolean(value) => Value::Boolean(value),
+            Token::Set(l) => Value::Set(list(l)?),
+            Token::Double(s) => Value::Double(String::from(s)),
+            Token::BigNumber(s) => Value::BigNumber(String::from(s)),
+            Token::BigErr(s) => Value::BigErr(text(s)?),
+            Token::VerbatimString(format, s) => Value::VerbatimString(text(format)?, text(s)?),
+            Token::Map(l) => Value::Map(list(l)?),
+            Token::Push(l) => Value::Push(list(l)?),
+            Token::ArrayHeader(len) => Value::ArrayHeader(len),
+            Token::Null => Value::Null,
+            Token::Attribute(l) => Value::Attribute(list(Some(l))?.unwrap_or_default()),
+            // 各个chunk拼接成一个完整的bulk string
+            Token::StreamedString(chunks) => Value::BulkString(Some(
+                chunks
+                    .iter()
+                    .flat_map(|chunk| chunk.as_bytes())
+                    .copied()
+                    .collect(),
+            )),
+        };
+        Ok(value)
+    }
+}
+
+impl Value {
+    // 编码成RESP3
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf, Protocol::Resp3);
+        buf
+    }
+
+    // 发给没有HELLO 3的RESP2客户端
+    pub fn encode_resp2(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf, Protocol::Resp2);
+        buf
+    }
+
+    // Resp2时把RESP3独有的类型换成RESP2里对应的写法
+    pub fn encode_into(&self, buf: &mut Vec<u8>, protocol: Protocol) {
+        let resp2 = protocol == Protocol::Resp2;
+        match self {
+            // 带换行符的文本没法写在一行里, 改用bulk string
+            Value::SimpleString(text) if has_newline(text) => {
+                write_bulk(buf, b'$', text.as_bytes())
+            }
+            Value::SimpleString(text) => write_line(buf, b'+', text.as_bytes()),
+            Value::Error(text) | Value::BigErr(text) if resp2 => write_error(buf, text),
+            Value::Error(text) if has_newline(text) => write_bulk(buf, b'!', text.as_bytes()),
+            Value::Error(text) => write_line(buf, b'-', text.as_bytes()),
+            Value::Integer(value) => write_line(buf, b':', value.to_string().as_bytes()),
+            Value::BulkString(Some(bytes)) => write_bulk(buf, b'$', bytes),
+            Value::BulkString(None) => buf.extend_from_slice(b"$-1\r\n"),
+            Value::Array(list) => write_list(buf, b'*', list, protocol),
+            Value::Boolean(value) if resp2 => {
+                write_line(buf, b':', if *value { b"1" } else { b"0" })
+            }
+            Value::Boolean(value) => write_line(buf, b'#', if *value { b"t" } else { b"f" }),
+            Value::Set(list) => write_list(buf, if resp2 { b'*' } else { b'~' }, list, protocol),
+            Value::Double(text) | Value::BigNumber(text) if resp2 => {
+                write_bulk(buf, b'$', text.as_bytes())
+            }
+            Value::Double(text) => write_line(buf, b',', text.as_bytes()),
+            Value::BigNumber(text) => write_line(buf, b'(', text.as_bytes()),
+            Value::BigErr(text) => write_bulk(buf, b'!', text.as_bytes()),
+            Value::VerbatimString(_, text) if resp2 => write_bulk(buf, b'$', text.as_bytes()),
+            Value::VerbatimString(format, text) => {
+                write_header(buf, b'=', format.len() + 1 + text.len());
+                buf.extend_from_slice(format.as_bytes());
+                buf.push(b':');
+                buf.extend_from_slice(text.as_bytes());
+                buf.extend_from_slice(b"\r\n");
+            }
+            Value::Map(Some(list)) => {
+                write_pairs(buf, if resp2 { b'*' } else { b'%' }, list, protocol)
+            }
+            Value::Map(None) => write_list(buf, b'*', &None, protocol),
+            Value::Push(list) => write_list(buf, if resp2 { b'*' } else { b'>' }, list, protocol),
+            Value::ArrayHeader(len) => write_header(buf, b'*', *len),
+            Value::Null if resp2 => buf.extend_from_slice(b"$-1\r\n"),
+            Value::Null => buf.extend_from_slice(b"_\r\n"),
+            // RESP2没有attribute, 直接丢掉
+            Value::Attribute(_) if resp2 => {}
+            Value::Attribute(list) => write_pairs(buf, b'|', list, protocol),
+        }
+    }
+
+    // 和Token::type_name的名字一致
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::SimpleString(_) => "simple_string",
+            Value::Error(_) => "error",
+            Value::Integer(_) => "integer",
+            Value::BulkString(_) => "bulk_string",
+            Value::Array(_) => "array",
+            Value::Boolean(_) => "boolean",
+            Value::Set(_) => "set",
+            Value::Double(_) => "double",
+            Value::BigNumber(_) => "big_number",
+            Value::BigErr(_) => "big_error",
+            Value::VerbatimString(_, _) => "verbatim_string",
+            Value::Map(_) => "map",
+            Value::Push(_) => "push",
+            Value::ArrayHeader(_) => "array_header",
+            Value::Null => "null",
+            Value::Attribute(_) => "attribute",
+        }
+    }
+
+    pub fn type_mismatch(&self, expected: &'static str) -> Error {
+        Error::TypeMismatch {
+            expected,
+            found: self.type_name(),
+        }
+    }
+
+    pub fn as_i64(&self) -> Result<i64, Error> {
+        match self {
+            Value::Integer(value) => Ok(*value),
+            _ => Err(self.type_mismatch("integer")),
+        }
+    }
+
+    // 和Token::as_str一样, bulk string需要是合法的utf8
+    pub fn as_str(&self) -> Result<&str, Error> {
+        match self {
+            Value::SimpleString(text)
+            | Value::Error(text)
+            | Value::BigErr(text)
+            | Value::VerbatimString(_, text) => Ok(text),
+            Value::BulkString(Some(bytes)) => core::str::from_utf8(bytes).map_err(Error::Utf8),
+            _ => Err(self.type_mismatch("string")),
+        }
+    }
+
+    pub fn as_array(&self) -> Result<&[Value], Error> {
+        match self {
+            Value::Array(Some(list)) | Value::Set(Some(list)) | Value::Push(Some(list)) => Ok(list),
+            _ => Err(self.type_mismatch("array")),
+        }
+    }
+
+    // RESP3的map, 或者RESP2里键值交替排列的数组
+    pub fn as_map(&self) -> Result<Vec<(&Value, &Value)>, Error> {
+        match self {
+            Value::Map(Some(list)) | Value::Array(Some(list)) if list.len() % 2 == 0 => Ok(list
+                .chunks_exact(2)
+                .map(|pair| (&pair[0], &pair[1]))
+                .collect()),
+            _ => Err(self.type_mismatch("map")),
+        }
+    }
+}
+
+fn write_line(buf: &mut Vec<u8>, symbol: u8, text: &[u8]) {
+    buf.push(symbol);
+    buf.extend_from_slice(text);
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn has_newline(text: &str) -> bool {
+    text.bytes().any(|c| c == b'\r' || c == b'\n')
+}
+
+// RESP2只有单行的错误, 和redis一样把换行符换成空格
+fn write_error(buf: &mut Vec<u8>, text: &str) {
+    buf.push(b'-');
+    buf.extend(text.bytes().map(|c| match c {
+        b'\r' | b'\n' => b' ',
+        c => c,
+    }));
+    buf.extend_from_slice(b"\r\n");
+}
+
+fn write_bulk(buf: &mut Vec<u8>, symbol: u8, bytes: &[u8]) {
+    write_header(buf, symbol, bytes.len());
+    buf.extend_from_slice(bytes);
+    buf.extend_from_slice(b"\r\n");
+}
+
+// RESP3没有null set/map/push, 统一写成null
+fn write_list(buf: &mut Vec<u8>, symbol: u8, list: &Option<Vec<Value>>, protocol: Protocol) {
+    match list {
+        Some(list) => {
+            write_header(buf, symbol, list.len());
+            for value in list {
+                value.encode_into(buf, protocol);
+            }
+        }
+        None if symbol == b'*' => buf.extend_from_slice(b"*-1\r\n"),
+        None => buf.extend_from_slice(b"_\r\n"),
+    }
+}
+
+// 键值对交替排列, 奇数个元素时给最后一个key补上null, 和Serialize的处理一致
+// *表示RESP2里展开成数组, 长度是元素个数而不是键值对个数
+fn write_pairs(buf: &mut Vec<u8>, symbol: u8, list: &[Value], protocol: Protocol) {
+    let pairs = list.len().div_ceil(2);
+    write_header(buf, symbol, if symbol == b'*' { pairs * 2 } else { pairs });
+    for value in list {
+        value.encode_into(buf, protocol);
+    }
+    if list.len() % 2 == 1 {
+        Value::Null.encode_into(buf, protocol);
+    }
+}
+
+impl Token<'_> {
+    // 消耗token直接转换, 聚合类型的元素逐个move过去, 不会先clone一份
+    // &str的文本一定是合法的utf8, 所以转换不会失败
+    pub fn into_owned(self) -> Value {
+        match Value::try_from(self) {
+            Ok(value) => value,
+            Err(_) => unreachable!("str tokens are always valid utf-8"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, S: ?Sized + Input> Token<'a, S> {
+    // RESP3的map, 或者RESP2里键值交替排列的数组, 比如CONFIG GET的回复
+    pub fn into_map(self) -> Option<HashMap<Value, Value>> {
+        let list = match self {
+            Token::Map(Some(list)) => list,
+            Token::Array(Some(list)) if list.len() % 2 == 0 => list,
+            _ => return None,
+        };
+        let mut map = HashMap::with_capacity(list.len() / 2);
+        let mut list = list.into_iter();
+        while let (Some(key), Some(value)) = (list.next(), list.next()) {
+            map.insert(Value::try_from(key).ok()?, Value::try_from(value).ok()?);
+        }
+        Some(map)
+    }
+}
+
+// parse_all的嵌套层数上限, 解析是递归的, 嵌套过深会栈溢出直接abort, 不是可以捕获的panic
+// debug构建下每层大约用掉5KB的栈, 128层在2MB栈的线程里也有足够的余量
+pub const PARSE_ALL_MAX_DEPTH: usize = 128;
+
+// 解析全部frame, 对任意输入都不会panic, 可以直接作为fuzz target
+// 嵌套超过PARSE_ALL_MAX_DEPTH层时返回DepthExceeded
+pub fn parse_all(input: &[u8]) -> Result<Vec<Value>, Error> {
+    let options = ParserBuilder::new()
+        .max_depth(PARSE_ALL_MAX_DEPTH)
+        .options();
+    let mut lexer = Lexer::with_options(input, options);
+    let mut values = Vec::new();
+    loop {
+        let rest = input.get(lexer.position()..).unwrap_or_default();
+        if rest.iter().all(is_frame_whitespace) {
+            return Ok(values);
+        }
+        match lexer.next() {
+            Some(token) => values.push(Value::try_from(token?)?),
+            None => return Err(Error::Incomplete),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_all, Value};
+    use crate::parser::{Error, Lexer, Protocol, Token};
+
+    #[test]
+    fn test_from_token() {
+        let mut lexer = Lexer::new("*3\r\n+OK\r\n$3\r\nfoo\r\n%1\r\n:1\r\n$-1\r\n");
+        let value = Value::try_from(lexer.next().unwrap().unwrap());
+        assert_eq!(
+            value,
+            Ok(Value::Array(Some(vec![
+                Value::SimpleString("OK".to_string()),
+                Value::BulkString(Some(b"foo".to_vec())),
+                Value::Map(Some(vec![Value::Integer(1), Value::BulkString(None)])),
+            ])))
+        );
+    }
+
+    #[test]
+    fn test_from_streamed_string() {
+        let mut lexer = Lexer::new("$?\r\n;4\r\nHell\r\n;1\r\no\r\n;0\r\n");
+        let value = Value::try_from(lexer.next().unwrap().unwrap());
+        assert_eq!(value, Ok(Value::BulkString(Some(b"Hello".to_vec()))));
+    }
+
+    #[test]
+    fn test_from_byte_token() {
+        let mut lexer = Lexer::new(&b"$2\r\n\xff\x00\r\n+\xff\r\n"[..]);
+        let token = lexer.next().unwrap().unwrap();
+        assert_eq!(token, Token::BulkString(Some(&b"\xff\x00"[..])));
+        assert_eq!(
+            Value::try_from(token),
+            Ok(Value::BulkString(Some(vec![0xff, 0x00])))
+        );
+
+        let token = lexer.next().unwrap().unwrap();
+        assert!(matches!(Value::try_from(token), Err(Error::Utf8(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_into_map() {
+        let expected = [
+            (
+                Value::BulkString(Some(b"maxmemory".to_vec())),
+                Value::Integer(0),
+            ),
+            (
+                Value::BulkString(Some(b"appendonly".to_vec())),
+                Value::BulkString(Some(b"no".to_vec())),
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut lexer =
+            Lexer::new("%2\r\n$9\r\nmaxmemory\r\n:0\r\n$10\r\nappendonly\r\n$2\r\nno\r\n");
+        assert_eq!(lexer.next().unwrap().unwrap().into_map(), Some(expected));
+
+        let mut lexer = Lexer::new(
+            "*4\r\n$9\r\nmaxmemory\r\n:0\r\n$10\r\nappendonly\r\n$2\r\nno\r\n*1\r\n:1\r\n",
+        );
+        let map = lexer.next().unwrap().unwrap().into_map().unwrap();
+        assert_eq!(
+            map.get(&Value::BulkString(Some(b"appendonly".to_vec()))),
+            Some(&Value::BulkString(Some(b"no".to_vec())))
+        );
+        // 奇数长度的数组不能组成键值对
+        assert_eq!(lexer.next().unwrap().unwrap().into_map(), None);
+        assert_eq!(Token::<str>::Integer(1).into_map(), None);
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let value = Value::Array(Some(vec![
+            Value::SimpleString("OK".to_string()),
+            Value::Error("ERR bad".to_string()),
+            Value::Integer(-42),
+            Value::BulkString(Some(b"a\r\nb".to_vec())),
+            Value::BulkString(None),
+            Value::Array(None),
+            Value::Boolean(true),
+            Value::Set(Some(vec![Value::Integer(1)])),
+            Value::Double("1.5".to_string()),
+            Value::BigNumber("123456789012345678901234567890".to_string()),
+            Value::BigErr("SYNTAX invalid".to_string()),
+            Value::VerbatimString("txt".to_string(), "Some string".to_string()),
+            Value::Map(Some(vec![
+                Value::SimpleString("key".to_string()),
+                Value::Map(Some(vec![])),
+            ])),
+            Value::Push(Some(vec![Value::SimpleString("message".to_string())])),
+            Value::Null,
+        ]));
+        let bytes = value.encode();
+        assert_eq!(parse_all(&bytes), Ok(vec![value]));
+
+        assert_eq!(
+            Value::Map(Some(vec![Value::Integer(1), Value::Boolean(false)])).encode(),
+            b"%1\r\n:1\r\n#f\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_newline() {
+        let text = "a\r\nb".to_string();
+        let value = Value::Array(Some(vec![
+            Value::SimpleString(text.clone()),
+            Value::Error(text.clone()),
+            Value::BigErr(text.clone()),
+        ]));
+        assert_eq!(
+            parse_all(&value.encode()),
+            Ok(vec![Value::Array(Some(vec![
+                Value::BulkString(Some(text.clone().into_bytes())),
+                Value::BigErr(text.clone()),
+                Value::BigErr(text.clone()),
+            ]))])
+        );
+        assert_eq!(
+            parse_all(&value.encode_resp2()),
+            Ok(vec![Value::Array(Some(vec![
+                Value::BulkString(Some(text.into_bytes())),
+                Value::Error("a  b".to_string()),
+                Value::Error("a  b".to_string()),
+            ]))])
+        );
+    }
+
+    #[test]
+    fn test_encode_odd_pairs() {
+        let key = Value::SimpleString("key".to_string());
+        let padded = vec![key.clone(), Value::Null];
+        let map = Value::Map(Some(vec![key.clone()]));
+        assert_eq!(map.encode(), b"%1\r\n+key\r\n_\r\n");
+        assert_eq!(
+            parse_all(&map.encode()),
+            Ok(vec![Value::Map(Some(padded.clone()))])
+        );
+        assert_eq!(
+            parse_all(&map.encode_resp2()),
+            Ok(vec![Value::Array(Some(vec![
+                key.clone(),
+                Value::BulkString(None)
+            ]))])
+        );
+
+        let attribute = Value::Attribute(vec![key]);
+        assert_eq!(attribute.encode(), b"|1\r\n+key\r\n_\r\n");
+        // attribute后面要跟着被修饰的数据
+        let mut buf = attribute.encode();
+        Value::Integer(1).encode_into(&mut buf, Protocol::Resp3);
+        let buf = String::from_utf8(buf).unwrap();
+        let mut lexer = Lexer::new(buf.as_str());
+        let (attribute, token) = lexer.with_attributes().next().unwrap().unwrap();
+        assert_eq!(
+            Value::Attribute(
+                attribute
+                    .unwrap()
+                    .into_iter()
+                    .map(Token::into_owned)
+                    .collect()
+            ),
+            Value::Attribute(padded)
+        );
+        assert_eq!(token.into_owned(), Value::Integer(1));
+    }
+
+    #[test]
+    fn test_encode_resp2() {
+        let value = Value::Map(Some(vec![
+            Value::SimpleString("flag".to_string()),
+            Value::Boolean(true),
+            Value::SimpleString("score".to_string()),
+            Value::Double("1.5".to_string()),
+            Value::Null,
+            Value::Set(Some(vec![])),
+        ]));
+        assert_eq!(
+            value.encode_resp2(),
+            b"*6\r\n+flag\r\n:1\r\n+score\r\n$3\r\n1.5\r\n$-1\r\n*0\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_protocols() {
+        let cases: [(Value, &[u8], &[u8]); 6] = [
+            (Value::Boolean(true), b"#t\r\n", b":1\r\n"),
+            (Value::Boolean(false), b"#f\r\n", b":0\r\n"),
+            (Value::Null, b"_\r\n", b"$-1\r\n"),
+            (
+                Value::Double("3.14".to_string()),
+                b",3.14\r\n",
+                b"$4\r\n3.14\r\n",
+            ),
+            (
+                Value::Map(Some(vec![
+                    Value::SimpleString("a".to_string()),
+                    Value::Integer(1),
+                ])),
+                b"%1\r\n+a\r\n:1\r\n",
+                b"*2\r\n+a\r\n:1\r\n",
+            ),
+            (
+                Value::Push(Some(vec![Value::Boolean(true)])),
+                b">1\r\n#t\r\n",
+                b"*1\r\n:1\r\n",
+            ),
+        ];
+        for (value, resp3, resp2) in cases {
+            assert_eq!(value.encode(), resp3);
+            assert_eq!(value.encode_resp2(), resp2);
+
+            let mut buf = Vec::new();
+            value.encode_into(&mut buf, Protocol::Resp2);
+            assert_eq!(buf, resp2);
+        }
+    }
+
+    #[test]
+    fn test_parse_all() {
+        assert_eq!(
+            parse_all(b"+OK\r\n:1\r\n"),
+            Ok(vec![
+                Value::SimpleString("OK".to_string()),
+                Value::Integer(1)
+            ])
+        );
+        assert_eq!(parse_all(b""), Ok(vec![]));
+        assert_eq!(parse_all(b"+OK\r\n$5\r\nab"), Err(Error::Incomplete));
+        assert_eq!(parse_all(b"?"), Err(Error::UnknownType('?')));
+        // 结尾只允许lexer认可的空白
+        assert_eq!(parse_all(b"+OK\r\n \r\n").map(|v| v.len()), Ok(1));
+        assert_eq!(parse_all(b"+OK\r\n\x0c"), Err(Error::UnknownType('\x0c')));
+    }
+
+    #[test]
+    fn test_typed_getters() {
+        let values = parse_all(b"*2\r\n:1\r\n$3\r\nfoo\r\n%1\r\n+a\r\n:2\r\n").unwrap();
+        let array = values[0].as_array().unwrap();
+        assert_eq!(array[0].as_i64(), Ok(1));
+        assert_eq!(array[1].as_str(), Ok("foo"));
+
+        let map = values[1].as_map().unwrap();
+        assert_eq!(
+            map,
+            vec![(&Value::SimpleString("a".to_string()), &Value::Integer(2))]
+        );
+        // 偶数长度的数组也可以当作map
+        assert_eq!(values[0].as_map().unwrap().len(), 1);
+
+        let mismatch = |expected, found| Error::TypeMismatch { expected, found };
+        assert_eq!(
+            array[1].as_i64().unwrap_err(),
+            mismatch("integer", "bulk_string")
+        );
+        assert_eq!(
+            array[0].as_str().unwrap_err(),
+            mismatch("string", "integer")
+        );
+        assert_eq!(values[1].as_array().unwrap_err(), mismatch("array", "map"));
+        assert_eq!(
+            Value::Array(None).as_array().unwrap_err(),
+            mismatch("array", "array")
+        );
+        assert_eq!(
+            Value::Array(Some(vec![Value::Null])).as_map().unwrap_err(),
+            mismatch("map", "array")
+        );
+        assert!(matches!(
+            Value::BulkString(Some(vec![0xff])).as_str(),
+            Err(Error::Utf8(_))
+        ));
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let mut data = String::from("*10000\r\n");
+        for i in 0..10000 {
+            data.push_str(&format!("*2\r\n:{}\r\n+v\r\n", i));
+        }
+        let mut lexer = Lexer::new(data.as_str());
+        let value = lexer.next().unwrap().unwrap().into_owned();
+        let list = value.as_array().unwrap();
+        assert_eq!(list.len(), 10000);
+        assert_eq!(
+            list[9999],
+            Value::Array(Some(vec![
+                Value::Integer(9999),
+                Value::SimpleString("v".to_string()),
+            ]))
+        );
+
+        assert_eq!(Token::Null.into_owned(), Value::Null);
+        assert_eq!(
+            Token::VerbatimString("txt", "hi").into_owned(),
+            Value::VerbatimString("txt".to_string(), "hi".to_string())
+        );
+    }
+}
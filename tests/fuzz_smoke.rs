@@ -0,0 +1,108 @@
+// 用一批畸形的输入调用parse_all, 只要求不panic
+use redis_parser::{parse_all, Error, Value, PARSE_ALL_MAX_DEPTH};
+
+const CORPUS: &[&[u8]] = &[
+    b"",
+    b"\r\n",
+    b"+",
+    b"+OK",
+    b"+OK\r",
+    b"-",
+    b":",
+    b":-",
+    b":+",
+    b":abc\r\n",
+    b":99999999999999999999999\r\n",
+    b"$",
+    b"$-",
+    b"$5\r\nhi\r\n",
+    b"$9223372036854775807\r\nabc\r\n",
+    b"$-9223372036854775808\r\n",
+    b"*",
+    b"*-5\r\n",
+    b"*3\r\n:1\r\n:2\r\n",
+    b"*9223372036854775807\r\n",
+    b"%9223372036854775807\r\n",
+    b"|9223372036854775807\r\n",
+    b"%4611686018427387904\r\n",
+    b"~-1\r\n",
+    b">1\r\n",
+    b"#\r\n",
+    b"#x\r\n",
+    b",\r\n",
+    b",e\r\n",
+    b",.\r\n",
+    b"(\r\n",
+    b"(-\r\n",
+    b"!\r\n",
+    b"=\r\n",
+    b"=-1\r\n",
+    b"=9223372036854775807\r\ntxt:abc\r\n",
+    b"=18446744073709551615\r\n",
+    b"=10\r\ntxtSomeXXX\r\n",
+    b"=2\r\nt\r\n",
+    b"|1\r\n",
+    b"|1\r\n+k\r\n+v\r\n",
+    b"\xff\xfe\xfd",
+    b"+\xff\r\n",
+    b"$2\r\n\xff\xfe\r\n",
+];
+
+#[test]
+fn test_corpus() {
+    for input in CORPUS {
+        let _ = parse_all(input);
+    }
+}
+
+#[test]
+fn test_prefixes() {
+    let input: &[u8] =
+        b"*3\r\n$3\r\nfoo\r\n%1\r\n+k\r\n,1.5e-3\r\n~2\r\n#t\r\n(123\r\n=15\r\ntxt:Some string\r\n";
+    for end in 0..=input.len() {
+        let _ = parse_all(&input[..end]);
+    }
+}
+
+#[test]
+fn test_pseudo_random() {
+    // 固定种子的线性同余生成器, 保证每次运行的输入一样
+    let mut seed: u32 = 0x2545_f491;
+    let alphabet = b"+-:$*~%#,(!=>|_.?0123456789-\r\ntfe:x";
+    for _ in 0..2000 {
+        let mut input = Vec::new();
+        for _ in 0..32 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            input.push(alphabet[(seed >> 16) as usize % alphabet.len()]);
+        }
+        let _ = parse_all(&input);
+    }
+}
+
+#[test]
+fn test_deep_nesting() {
+    // 嵌套很深的输入返回错误, 不会栈溢出
+    for prefix in [b"*1\r\n", b"~1\r\n", b">1\r\n"] {
+        let mut input = prefix.repeat(200_000);
+        input.extend_from_slice(b":1\r\n");
+        assert_eq!(
+            parse_all(&input),
+            Err(Error::DepthExceeded(PARSE_ALL_MAX_DEPTH))
+        );
+    }
+    let mut input = b"%1\r\n+k\r\n".repeat(200_000);
+    input.extend_from_slice(b":1\r\n");
+    assert_eq!(
+        parse_all(&input),
+        Err(Error::DepthExceeded(PARSE_ALL_MAX_DEPTH))
+    );
+
+    // 上限以内的嵌套正常解析
+    let mut input = b"*1\r\n".repeat(PARSE_ALL_MAX_DEPTH - 1);
+    input.extend_from_slice(b":1\r\n");
+    let mut value = Value::Integer(1);
+    for _ in 1..PARSE_ALL_MAX_DEPTH {
+        value = Value::Array(Some(vec![value]));
+    }
+    assert_eq!(parse_all(&input), Ok(vec![value]));
+}
@@ -0,0 +1,38 @@
+// 只用 core/alloc 的路径调用解析器, 检查 no_std 下的 API 可用
+// cargo test --no-default-features --test no_std
+#![no_std]
+
+extern crate alloc;
+extern crate std;
+
+use alloc::vec;
+use redis_parser::{Lexer, Parser, Token, Visitor};
+
+struct Sum(i64);
+
+impl<'a> Visitor<'a> for Sum {
+    fn on_integer(&mut self, value: i64) {
+        self.0 += value;
+    }
+}
+
+#[test]
+fn test_no_std_lexer() {
+    let mut lexer = Lexer::new("*2\r\n+OK\r\n:1\r\n");
+    assert_eq!(
+        lexer.next().unwrap(),
+        Ok(Token::Array(Some(vec![
+            Token::SimpleString("OK"),
+            Token::Integer(1),
+        ])))
+    );
+    assert_eq!(lexer.next(), None);
+}
+
+#[test]
+fn test_no_std_callbacks() {
+    let parser = Parser::new("*2\r\n:1\r\n:2\r\n:3\r\n");
+    let mut sum = Sum(0);
+    assert_eq!(parser.parse_with_callbacks(&mut sum), Ok(()));
+    assert_eq!(sum.0, 6);
+}